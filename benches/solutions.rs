@@ -0,0 +1,145 @@
+//! Benchmarks every registered generator and part, on the real inputs and on larger inputs built
+//! by tiling them.
+//!
+//! Criterion keeps its results under `target/criterion`, so branches can be compared with e.g.
+//!
+//! ```text
+//! git checkout main && cargo bench -- --save-baseline main
+//! git checkout my-branch && cargo bench -- --baseline main
+//! ```
+//!
+//! A subset can be selected with a filter such as `cargo bench -- day5/`.  Some days are also
+//! measured on synthetic inputs of millions of lines, which is where their alternative solutions
+//! pull ahead: day 1's `simd` variants (built with `--features nightly`) and day 3's `sorted`
+//! part 2.
+
+use std::{fs, hint::black_box, path::Path};
+
+use advent_of_code_2021::{
+    runner::{self, Solution},
+    synthetic,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// How many copies of the real input each synthetic input is built from.
+const SCALES: [usize; 3] = [1, 4, 16];
+
+/// Numbers of depths in the synthetic day 1 sweeps.
+const DAY1_DEPTHS: [usize; 2] = [1_000_000, 4_000_000];
+
+/// Numbers of readings in the synthetic day 3 reports.
+const DAY3_READINGS: [usize; 2] = [1_000_000, 4_000_000];
+
+/// Width of the synthetic day 3 readings, wide enough that a million of them are nearly all
+/// different.
+const DAY3_BITS: usize = 48;
+
+fn real_input(day: u32) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("input/2021/day{day}.txt"));
+    fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()))
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+/// Builds a larger, still valid, input for `day` out of `factor` copies of `input`.
+fn scale(day: u32, input: &str, factor: usize) -> String {
+    match day {
+        // bingo: keep a single line of draws, and repeat the boards
+        4 => {
+            let (numbers, boards) = input.split_once('\n').unwrap();
+            let mut out = numbers.to_string();
+            for _ in 0..factor {
+                out.push('\n');
+                out.push_str(boards);
+            }
+            out
+        }
+        // heightmap: tile the map, growing both dimensions so the area grows by `factor`
+        9 => {
+            let side = factor.isqrt();
+            let rows = input
+                .lines()
+                .map(|row| row.repeat(side))
+                .collect::<Vec<_>>()
+                .join("\n");
+            vec![rows; side].join("\n")
+        }
+        // everything else is a list of independent lines
+        _ => vec![input; factor].join("\n"),
+    }
+}
+
+fn bench_day(c: &mut Criterion, day: u32) {
+    let real = real_input(day);
+    let mut group = c.benchmark_group(format!("day{day}"));
+
+    for factor in SCALES {
+        let input = scale(day, &real, factor);
+        let label = if factor == 1 {
+            "real".to_string()
+        } else {
+            format!("x{factor}")
+        };
+        group.throughput(Throughput::Bytes(input.len() as u64));
+
+        let solutions = runner::find(day, None).collect::<Vec<&Solution>>();
+        // generators are shared between parts, so only measure one of them
+        if let Some(solution) = solutions.first() {
+            group.bench_with_input(BenchmarkId::new("generator", &label), &input, |b, i| {
+                b.iter(|| solution.generate(black_box(i)).unwrap())
+            });
+        }
+        for solution in solutions {
+            let runner = solution.generate(&input).unwrap();
+            let name = match solution.name {
+                Some(name) => format!("part{}_{name}", solution.part),
+                None => format!("part{}", solution.part),
+            };
+            group.bench_function(BenchmarkId::new(name, &label), |b| {
+                b.iter(|| runner.try_run().unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// Benchmarks every part of `day` on the synthetic inputs `input(size)` for each of `sizes`, which
+/// count lines.
+fn bench_large(c: &mut Criterion, day: u32, sizes: &[usize], input: impl Fn(usize) -> String) {
+    let mut group = c.benchmark_group(format!("day{day}_large"));
+    group.sample_size(10);
+    for &size in sizes {
+        let input = input(size);
+        group.throughput(Throughput::Elements(size as u64));
+        for solution in runner::find(day, None) {
+            let runner = solution.generate(&input).unwrap();
+            let name = match solution.name {
+                Some(name) => format!("part{}_{name}", solution.part),
+                None => format!("part{}", solution.part),
+            };
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter(|| runner.try_run().unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+fn solutions(c: &mut Criterion) {
+    for day in runner::days() {
+        bench_day(c, day);
+    }
+    bench_large(c, 1, &DAY1_DEPTHS, |depths| {
+        synthetic::day1(&mut ChaCha8Rng::seed_from_u64(2021), depths)
+    });
+    bench_large(c, 3, &DAY3_READINGS, |readings| {
+        synthetic::day3(&mut ChaCha8Rng::seed_from_u64(2021), readings, DAY3_BITS)
+    });
+}
+
+criterion_group!(benches, solutions);
+criterion_main!(benches);
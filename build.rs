@@ -0,0 +1,92 @@
+//! Scans `src/` for `#[aoc(dayN, partM)]` attributes and writes the solution table used by
+//! `runner::SOLUTIONS`, so a new day is picked up by the `aoc2021` binary as soon as it is
+//! registered with aoc-runner.  `#[cfg(...)]` attributes placed before the `#[aoc]` attribute are
+//! copied to the table entry, so feature-gated solutions are only listed when they are compiled.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+struct Entry {
+    day: u32,
+    part: u32,
+    name: Option<String>,
+    cfgs: Vec<String>,
+}
+
+/// Parses the arguments of a single `#[aoc(...)]` attribute, e.g. `day5, part2` or
+/// `day1, part1, simd`.
+fn parse_attribute(args: &str) -> Option<Entry> {
+    let mut idents = args.split(',').map(str::trim);
+    let day = idents.next()?.strip_prefix("day")?.parse().ok()?;
+    let part = idents.next()?.strip_prefix("part")?.parse().ok()?;
+    let name = idents.next().filter(|s| !s.is_empty()).map(String::from);
+    Some(Entry {
+        day,
+        part,
+        name,
+        cfgs: vec![],
+    })
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+
+    let mut entries = vec![];
+    for file in fs::read_dir("src").unwrap() {
+        let path = file.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        let source = fs::read_to_string(&path).unwrap();
+        let mut cfgs = vec![];
+        for line in source.lines().map(str::trim) {
+            if line.starts_with("#[cfg(") {
+                cfgs.push(line.to_string());
+            } else if let Some(args) = line
+                .strip_prefix("#[aoc(")
+                .and_then(|l| l.strip_suffix(")]"))
+            {
+                entries.extend(parse_attribute(args).map(|entry| Entry {
+                    cfgs: std::mem::take(&mut cfgs),
+                    ..entry
+                }));
+            } else if !line.starts_with("#[") && !line.starts_with("//") {
+                cfgs.clear();
+            }
+        }
+    }
+    entries.sort_by(|a, b| (a.day, a.part, &a.name).cmp(&(b.day, b.part, &b.name)));
+
+    // Mirror aoc-runner-derive's naming: `Day1Part1` / `day1_part1`, with an optional name
+    // appended as `Day1Part1NAME` / `day1_part1_name`.
+    let mut table = String::from("&[\n");
+    for Entry {
+        day,
+        part,
+        name,
+        cfgs,
+    } in entries
+    {
+        let (camel, snake) = match &name {
+            Some(n) => (
+                format!("Day{day}Part{part}{}", n.to_uppercase()),
+                format!("day{day}_part{part}_{}", n.to_lowercase()),
+            ),
+            None => (
+                format!("Day{day}Part{part}"),
+                format!("day{day}_part{part}"),
+            ),
+        };
+        for cfg in cfgs {
+            writeln!(table, "    {cfg}").unwrap();
+        }
+        writeln!(
+            table,
+            "    Solution {{ day: {day}, part: {part}, name: {name:?}, factory: <crate::Factory as crate::{camel}>::{snake} }},"
+        )
+        .unwrap();
+    }
+    table.push(']');
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("solutions.rs");
+    fs::write(out, table).unwrap();
+}
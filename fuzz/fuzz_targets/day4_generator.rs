@@ -0,0 +1,14 @@
+#![no_main]
+//! Feeds arbitrary input to day 4.  The bingo parser runs inside each part rather than as an
+//! aoc-runner generator, so this plays the whole game; both must reject bad input with an error
+//! rather than panicking or overflowing.
+
+use advent_of_code_2021::runner;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    for solution in runner::find(4, None) {
+        let _ = solution.run(&input);
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+//! Feeds arbitrary input to the day 5 generator, which must reject bad input with an error rather
+//! than panicking or overflowing.
+
+use advent_of_code_2021::runner;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    let solution = runner::find(5, None).next().unwrap();
+    let _ = solution.generate(&input);
+});
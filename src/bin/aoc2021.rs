@@ -0,0 +1,375 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+use advent_of_code_2021::{
+    day1,
+    day2::{self, inverse::Invertible, MovementModel},
+    day3,
+    runner::{self, Solution},
+    streaming::{self, StreamingSolution},
+    synthetic,
+};
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Runs the Advent of Code 2021 solutions in this crate.
+#[derive(Parser)]
+#[command(name = "aoc2021", args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Day to run.  Runs every implemented day if omitted.
+    #[arg(short, long)]
+    day: Option<u32>,
+
+    /// Part to run.  Runs every part of the selected day(s) if omitted.
+    #[arg(short, long)]
+    part: Option<u32>,
+
+    /// Input file, or `-` to read from stdin.  Defaults to `input/2021/day<N>.txt`.
+    #[arg(short, long, requires = "day")]
+    input: Option<PathBuf>,
+
+    /// Read the input a line at a time instead of loading it into memory.  Only parts that can
+    /// be solved in a single pass support this.
+    #[arg(long)]
+    stream: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Writes a random, valid input for a day to stdout.
+    Generate {
+        /// Day to generate an input for.
+        #[arg(short, long)]
+        day: u32,
+
+        /// Number of lines, commands, readings, boards or segments to generate; for day 9, the
+        /// side length of the heightmap.
+        #[arg(short, long, default_value_t = 1000)]
+        size: usize,
+
+        /// Width of each day 3 reading.  Up to 64, so that the answers fit.
+        #[arg(long, default_value_t = 12, value_parser = clap::value_parser!(u32).range(1..=64))]
+        bits: u32,
+
+        /// Seed for the random number generator.  The same seed always gives the same input.
+        #[arg(long, default_value_t = 2021)]
+        seed: u64,
+    },
+
+    /// Counts how often the sum of a sliding window of depths increases, as in day 1.  Part 1 uses
+    /// a window of 1 and part 2 a window of 3.
+    Sonar {
+        /// Number of consecutive depths summed in each window.
+        #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        window: u32,
+
+        /// Input file, or `-` to read from stdin.
+        #[arg(short, long, default_value = "input/2021/day1.txt")]
+        input: PathBuf,
+    },
+
+    /// Prints statistics about a day 1 sonar sweep.
+    Analyze {
+        /// Include a moving average over this many depths.  May be repeated.
+        #[arg(short = 'a', long = "average")]
+        averages: Vec<usize>,
+
+        /// Print the report as JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+
+        /// Input file, or `-` to read from stdin.
+        #[arg(short, long, default_value = "input/2021/day1.txt")]
+        input: PathBuf,
+    },
+
+    /// Writes the submarine's state after each day 2 command, as CSV or as an SVG plot.
+    Trace {
+        /// How commands move the submarine: part 1's `direct` or part 2's `aimed`.
+        #[arg(short, long, value_enum, default_value_t = Model::Aimed)]
+        model: Model,
+
+        #[arg(short, long, value_enum, default_value_t = TraceFormat::Csv)]
+        format: TraceFormat,
+
+        /// Input file, or `-` to read from stdin.
+        #[arg(short, long, default_value = "input/2021/day2.txt")]
+        input: PathBuf,
+    },
+
+    /// Writes a shortest day 2 course that ends at the given position and depth.
+    Script {
+        #[arg(short, long, value_enum, default_value_t = Model::Aimed)]
+        model: Model,
+
+        /// Horizontal position to end at.
+        #[arg(short, long)]
+        position: i32,
+
+        /// Depth to end at.
+        #[arg(short, long, allow_negative_numbers = true)]
+        depth: i32,
+    },
+
+    /// Prints the rates, ratings and bit counts of a day 3 diagnostic report, with the steps
+    /// taken to find each rating.
+    Diagnose {
+        /// Print the report as JSON instead of tables.
+        #[arg(long)]
+        json: bool,
+
+        /// Input file, or `-` to read from stdin.
+        #[arg(short, long, default_value = "input/2021/day3.txt")]
+        input: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Model {
+    Direct,
+    Aimed,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TraceFormat {
+    Csv,
+    Svg,
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+fn read_input(path: &Path) -> anyhow::Result<String> {
+    if is_stdin(path) {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .context("failed to read input from stdin")?;
+        Ok(input)
+    } else {
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+    }
+}
+
+fn open_input(path: &Path) -> anyhow::Result<Box<dyn BufRead>> {
+    if is_stdin(path) {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Runs a single solution and prints its answer and timings.  Returns false if it failed.
+fn run(solution: &Solution, input: &str) -> bool {
+    match solution.run(input) {
+        Ok(outcome) => {
+            println!(
+                "{solution}: {}\n\tgenerator: {:?},\n\trunner: {:?}\n",
+                outcome.answer, outcome.generator, outcome.runner
+            );
+            true
+        }
+        Err(e) => {
+            eprintln!("{solution}: FAILED: {e}\n");
+            false
+        }
+    }
+}
+
+/// Runs a streaming solution over the input at `path` and prints its answer and timing.  Returns
+/// false if it failed.
+fn run_streaming(solution: &StreamingSolution, path: &Path) -> anyhow::Result<bool> {
+    let mut input = open_input(path)?;
+    let start = Instant::now();
+    match solution.solve(&mut input) {
+        Ok(answer) => {
+            println!("{solution}: {answer}\n\trunner: {:?}\n", start.elapsed());
+            Ok(true)
+        }
+        Err(e) => {
+            eprintln!("{solution}: FAILED: {e}\n");
+            Ok(false)
+        }
+    }
+}
+
+fn generate(day: u32, size: usize, bits: usize, seed: u64) -> anyhow::Result<()> {
+    let Some(input) = synthetic::generate(day, size, bits, seed) else {
+        bail!("no day {day} input of size {size} can be generated");
+    };
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{input}")?;
+    Ok(())
+}
+
+fn sonar(window: u32, path: &Path) -> anyhow::Result<()> {
+    let depths = day1::generator(&read_input(path)?)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    let increases = day1::count_window_increases(&depths, window as usize);
+    println!("{increases}");
+    Ok(())
+}
+
+fn analyze(averages: &[usize], json: bool, path: &Path) -> anyhow::Result<()> {
+    let depths = day1::generator(&read_input(path)?)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    let report = day1::Report::new(&depths, averages);
+    let mut stdout = io::stdout().lock();
+    if json {
+        writeln!(stdout, "{}", report.to_json())?;
+    } else {
+        write!(stdout, "{report}")?;
+    }
+    Ok(())
+}
+
+fn trace(model: Model, format: TraceFormat, path: &Path) -> anyhow::Result<()> {
+    let commands = day2::generator(&read_input(path)?)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    let trajectory = match model {
+        Model::Direct => day2::Direct.trace(commands)?,
+        Model::Aimed => day2::Aimed.trace(commands)?,
+    };
+    let mut stdout = io::stdout().lock();
+    match format {
+        TraceFormat::Csv => write!(stdout, "{}", trajectory.to_csv())?,
+        TraceFormat::Svg => write!(stdout, "{}", trajectory.to_svg())?,
+    }
+    Ok(())
+}
+
+fn script(model: Model, position: i32, depth: i32) -> anyhow::Result<()> {
+    let script = match model {
+        Model::Direct => day2::Direct.script_to(position, depth),
+        Model::Aimed => day2::Aimed.script_to(position, depth),
+    }
+    .with_context(|| format!("can't reach position {position}, depth {depth}"))?;
+    let mut stdout = io::stdout().lock();
+    for command in script {
+        writeln!(stdout, "{command}")?;
+    }
+    Ok(())
+}
+
+fn diagnose(json: bool, path: &Path) -> anyhow::Result<()> {
+    let problem = day3::generator(&read_input(path)?)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    let report = day3::DiagnosticReport::new(&problem);
+    let mut stdout = io::stdout().lock();
+    if json {
+        writeln!(stdout, "{}", report.to_json())?;
+    } else {
+        write!(stdout, "{report}")?;
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<ExitCode> {
+    let args = Args::parse();
+    match args.command {
+        Some(Command::Generate {
+            day,
+            size,
+            bits,
+            seed,
+        }) => {
+            generate(day, size, bits as usize, seed)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Sonar { window, input }) => {
+            sonar(window, &input)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Analyze {
+            averages,
+            json,
+            input,
+        }) => {
+            analyze(&averages, json, &input)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Trace {
+            model,
+            format,
+            input,
+        }) => {
+            trace(model, format, &input)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Script {
+            model,
+            position,
+            depth,
+        }) => {
+            script(model, position, depth)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Diagnose { json, input }) => {
+            diagnose(json, &input)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
+    }
+
+    let days = match args.day {
+        Some(day) => vec![day],
+        None if args.stream => runner::days()
+            .into_iter()
+            .filter(|&day| streaming::find(day, None).next().is_some())
+            .collect(),
+        None => runner::days(),
+    };
+
+    let mut success = true;
+    for day in days {
+        let path = args
+            .input
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("input/2021/day{day}.txt")));
+
+        if args.stream {
+            let solutions = streaming::find(day, args.part).collect::<Vec<_>>();
+            if solutions.is_empty() {
+                bail!("no streaming solution for the selected part(s) of day {day}");
+            }
+            if is_stdin(&path) && solutions.len() > 1 {
+                bail!("--part is required when streaming from stdin");
+            }
+            for solution in solutions {
+                success &= run_streaming(solution, &path)?;
+            }
+            continue;
+        }
+
+        let solutions = runner::find(day, args.part).collect::<Vec<_>>();
+        if solutions.is_empty() {
+            match args.part {
+                Some(part) => bail!("no solution registered for day {day} part {part}"),
+                None => bail!("no solution registered for day {day}"),
+            }
+        }
+
+        let input = read_input(&path)?;
+
+        for solution in solutions {
+            success &= run(solution, &input);
+        }
+    }
+
+    Ok(if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -1,22 +1,451 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    io::BufRead,
+};
+
 use aoc_runner_derive::{aoc, aoc_generator};
+use serde::Serialize;
+
+use crate::{
+    error::{self, parse_field, Error},
+    streaming::for_each_line,
+};
 
+/// Parses a sonar sweep: one depth per line.
 #[aoc_generator(day1)]
-fn generator(input: &str) -> Vec<i32> {
-    input.split('\n').map(|x| x.parse().unwrap()).collect()
+pub fn generator(input: &str) -> error::Result<Vec<i32>> {
+    if input.trim().is_empty() {
+        return Err(Error::EmptyInput);
+    }
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| parse_field(i + 1, line, line))
+        .collect()
 }
 
-#[aoc(day1, part1)]
-fn part1(input: &[i32]) -> i32 {
+/// Counts the windows of `N` depths whose last depth is deeper than their first.  With the
+/// `nightly` feature this uses `array_windows`, which lets the compiler see the window size.
+#[cfg(feature = "nightly")]
+fn count_increases<const N: usize>(input: &[i32]) -> i32 {
     input
-        .array_windows::<2>()
-        .map(|x| (x[0] < x[1]) as i32)
+        .array_windows::<N>()
+        .map(|x| (x[0] < x[N - 1]) as i32)
         .sum()
 }
 
-#[aoc(day1, part2)]
-fn part2(input: &[i32]) -> i32 {
+#[cfg(not(feature = "nightly"))]
+fn count_increases<const N: usize>(input: &[i32]) -> i32 {
+    portable_count_increases::<N>(input)
+}
+
+/// Same as `count_increases`, but using only stable APIs.
+#[cfg_attr(all(feature = "nightly", not(test)), allow(dead_code))]
+fn portable_count_increases<const N: usize>(input: &[i32]) -> i32 {
+    input.windows(N).map(|x| (x[0] < x[N - 1]) as i32).sum()
+}
+
+/// Counts how often the sum of `window` consecutive depths is larger than the sum of the
+/// previous `window`.  Consecutive sums share all but one depth, so this only compares each depth
+/// with the one `window` measurements before it.  Parts 1 and 2 use windows of 1 and 3.
+pub fn count_window_increases(input: &[i32], window: usize) -> i32 {
     input
-        .array_windows::<4>()
-        .map(|x| (x[0] < x[3]) as i32)
+        .iter()
+        .zip(input.iter().skip(window))
+        .map(|(a, b)| (a < b) as i32)
         .sum()
 }
+
+/// Number of depths compared at once by `simd_count_window_increases`.
+#[cfg(feature = "nightly")]
+const LANES: usize = 16;
+
+/// Same as `count_window_increases`, but compares `LANES` pairs of depths at a time.
+#[cfg(feature = "nightly")]
+pub fn simd_count_window_increases(input: &[i32], window: usize) -> i32 {
+    use std::simd::prelude::*;
+
+    let pairs = input.len().saturating_sub(window);
+    let (earlier, later) = (&input[..pairs], &input[input.len() - pairs..]);
+    let (zeros, ones) = (Simd::<i32, LANES>::splat(0), Simd::splat(1));
+    let mut counts = zeros;
+    for (a, b) in earlier.chunks_exact(LANES).zip(later.chunks_exact(LANES)) {
+        let increased = Simd::from_slice(a).simd_lt(Simd::from_slice(b));
+        counts += increased.select(ones, zeros);
+    }
+    let done = pairs - pairs % LANES;
+    counts.reduce_sum() + count_window_increases(&input[done..], window)
+}
+
+/// Streaming version of `count_window_increases`, reading one depth per line and keeping only the
+/// last `window + 1` depths in memory.
+pub(crate) fn count_window_increases_streaming(
+    input: impl BufRead,
+    window: usize,
+) -> error::Result<i32> {
+    let mut recent = VecDeque::with_capacity(window + 1);
+    let mut count = 0;
+    let lines = for_each_line(input, |line_no, line| {
+        let depth: i32 = parse_field(line_no, line, line)?;
+        recent.push_back(depth);
+        if recent.len() > window {
+            let first = recent.pop_front().unwrap();
+            count += (first < depth) as i32;
+        }
+        Ok(())
+    })?;
+    if lines == 0 {
+        return Err(Error::EmptyInput);
+    }
+    Ok(count)
+}
+
+/// A stretch of consecutive depths, each deeper than the one before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Run {
+    /// Index of the first depth in the run.
+    pub start: usize,
+    /// Number of depths in the run.
+    pub len: usize,
+}
+
+/// The change between two consecutive depths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Step {
+    /// Index of the first of the two depths.
+    pub index: usize,
+    pub from: i32,
+    pub to: i32,
+}
+
+/// The average of every `width` consecutive depths, in order.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MovingAverage {
+    pub width: usize,
+    pub values: Vec<f64>,
+}
+
+/// How many times consecutive depths differ by `delta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Bucket {
+    pub delta: i64,
+    pub count: usize,
+}
+
+/// Statistics about a sonar sweep.  Printed as a table with `Display`, or as JSON with
+/// [`Report::to_json`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub depths: usize,
+    /// The longest strictly increasing run, or `None` if there are no depths.  Ties go to the
+    /// earliest run.
+    pub longest_increasing_run: Option<Run>,
+    /// The step with the largest decrease in depth, or `None` if the depth never decreases.  Ties
+    /// go to the earliest step.
+    pub largest_drop: Option<Step>,
+    pub moving_averages: Vec<MovingAverage>,
+    /// Counts of the differences between consecutive depths, sorted by difference.
+    pub delta_histogram: Vec<Bucket>,
+}
+
+impl Report {
+    /// Analyses `depths`, including a moving average for each of `widths`.
+    pub fn new(depths: &[i32], widths: &[usize]) -> Report {
+        Report {
+            depths: depths.len(),
+            longest_increasing_run: longest_increasing_run(depths),
+            largest_drop: largest_drop(depths),
+            moving_averages: widths
+                .iter()
+                .map(|&width| MovingAverage {
+                    width,
+                    values: moving_average(depths, width),
+                })
+                .collect(),
+            delta_histogram: delta_histogram(depths),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("reports always serialize")
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "depths:                 {}", self.depths)?;
+        write!(f, "longest increasing run: ")?;
+        match self.longest_increasing_run {
+            Some(Run { start, len }) => writeln!(f, "{len} depths from index {start}")?,
+            None => writeln!(f, "-")?,
+        }
+        write!(f, "largest drop:           ")?;
+        match self.largest_drop {
+            Some(Step { index, from, to }) => {
+                let drop = i64::from(from) - i64::from(to);
+                writeln!(f, "{drop} ({from} -> {to}) at index {index}")?
+            }
+            None => writeln!(f, "-")?,
+        }
+
+        writeln!(f, "\n{:>8} {:>8}", "delta", "count")?;
+        for Bucket { delta, count } in &self.delta_histogram {
+            writeln!(f, "{delta:>8} {count:>8}")?;
+        }
+
+        if !self.moving_averages.is_empty() {
+            write!(f, "\n{:>8}", "index")?;
+            for average in &self.moving_averages {
+                write!(f, " {:>10}", format!("avg{}", average.width))?;
+            }
+            writeln!(f)?;
+            let rows = self.moving_averages.iter().map(|a| a.values.len()).max();
+            for i in 0..rows.unwrap_or(0) {
+                write!(f, "{i:>8}")?;
+                for average in &self.moving_averages {
+                    match average.values.get(i) {
+                        Some(value) => write!(f, " {value:>10.2}")?,
+                        None => write!(f, " {:>10}", "")?,
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn longest_increasing_run(depths: &[i32]) -> Option<Run> {
+    let mut best: Option<Run> = None;
+    let mut start = 0;
+    for end in 1..=depths.len() {
+        if end == depths.len() || depths[end - 1] >= depths[end] {
+            let run = Run {
+                start,
+                len: end - start,
+            };
+            if best.is_none_or(|best| run.len > best.len) {
+                best = Some(run);
+            }
+            start = end;
+        }
+    }
+    best
+}
+
+fn largest_drop(depths: &[i32]) -> Option<Step> {
+    depths
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| w[1] < w[0])
+        .map(|(index, w)| Step {
+            index,
+            from: w[0],
+            to: w[1],
+        })
+        // `max_by_key` keeps the last maximum, so search backwards to find the first
+        .rev()
+        .max_by_key(|step| i64::from(step.from) - i64::from(step.to))
+}
+
+/// Averages every `width` consecutive depths, keeping a running sum that, like the counts above,
+/// only needs the depths `width` apart.  Empty if `width` is 0 or larger than the input.
+fn moving_average(depths: &[i32], width: usize) -> Vec<f64> {
+    if width == 0 || width > depths.len() {
+        return Vec::new();
+    }
+    let mut sum = depths[..width].iter().map(|&d| i64::from(d)).sum::<i64>();
+    let mut values = vec![sum as f64 / width as f64];
+    for (old, new) in depths.iter().zip(&depths[width..]) {
+        sum += i64::from(*new) - i64::from(*old);
+        values.push(sum as f64 / width as f64);
+    }
+    values
+}
+
+fn delta_histogram(depths: &[i32]) -> Vec<Bucket> {
+    let mut counts = BTreeMap::new();
+    for w in depths.windows(2) {
+        *counts.entry(i64::from(w[1]) - i64::from(w[0])).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(delta, count)| Bucket { delta, count })
+        .collect()
+}
+
+#[aoc(day1, part1)]
+fn part1(input: &[i32]) -> error::Result<i32> {
+    Ok(count_increases::<2>(input))
+}
+
+// Comparing the first and last depth of a 4-wide window is the same as comparing the sums of
+// its two 3-wide windows, since the middle two depths are shared.
+#[aoc(day1, part2)]
+fn part2(input: &[i32]) -> error::Result<i32> {
+    Ok(count_increases::<4>(input))
+}
+
+#[cfg(feature = "nightly")]
+#[aoc(day1, part1, simd)]
+fn part1_simd(input: &[i32]) -> error::Result<i32> {
+    Ok(simd_count_window_increases(input, 1))
+}
+
+#[cfg(feature = "nightly")]
+#[aoc(day1, part2, simd)]
+fn part2_simd(input: &[i32]) -> error::Result<i32> {
+    Ok(simd_count_window_increases(input, 3))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Counts increases the obvious way, by comparing each sum of `width` consecutive depths with
+    /// the next one.
+    fn naive_increases(input: &[i32], width: usize) -> i32 {
+        let sums = input
+            .windows(width)
+            .map(|w| w.iter().sum())
+            .collect::<Vec<i32>>();
+        sums.windows(2).filter(|w| w[0] < w[1]).count() as i32
+    }
+
+    #[test]
+    fn window_sizes_match_parts() {
+        let input = generator(include_str!("../input/2021/day1.txt")).unwrap();
+        assert_eq!(Ok(count_window_increases(&input, 1)), part1(&input));
+        assert_eq!(Ok(count_window_increases(&input, 3)), part2(&input));
+        assert_eq!(count_window_increases(&input, 0), 0);
+        assert_eq!(count_window_increases(&input, input.len()), 0);
+    }
+
+    #[test]
+    fn report_sample_data() {
+        let depths = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+        let report = Report::new(&depths, &[3]);
+        assert_eq!(report.depths, 10);
+        assert_eq!(
+            report.longest_increasing_run,
+            Some(Run { start: 0, len: 4 })
+        );
+        assert_eq!(
+            report.largest_drop,
+            Some(Step {
+                index: 3,
+                from: 210,
+                to: 200
+            })
+        );
+        let sums = [607, 618, 618, 617, 647, 716, 769, 792];
+        assert_eq!(
+            report.moving_averages[0].values,
+            sums.map(|s| s as f64 / 3.0)
+        );
+        assert_eq!(
+            report.delta_histogram.first(),
+            Some(&Bucket {
+                delta: -10,
+                count: 1
+            })
+        );
+        assert_eq!(
+            report
+                .delta_histogram
+                .iter()
+                .map(|b| b.count)
+                .sum::<usize>(),
+            9
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["longest_increasing_run"]["len"], 4);
+        assert!(report.to_string().contains("4 depths from index 0"));
+    }
+
+    #[test]
+    fn report_degenerate_inputs() {
+        let empty = Report::new(&[], &[0, 2]);
+        assert_eq!(empty.longest_increasing_run, None);
+        assert_eq!(empty.largest_drop, None);
+        assert!(empty.moving_averages.iter().all(|a| a.values.is_empty()));
+        assert!(empty.delta_histogram.is_empty());
+
+        let rising = Report::new(&[i32::MIN, 0, i32::MAX], &[]);
+        assert_eq!(
+            rising.longest_increasing_run,
+            Some(Run { start: 0, len: 3 })
+        );
+        assert_eq!(rising.largest_drop, None);
+        assert_eq!(
+            rising.delta_histogram,
+            [
+                Bucket {
+                    delta: i64::from(i32::MAX),
+                    count: 1
+                },
+                Bucket {
+                    delta: -i64::from(i32::MIN),
+                    count: 1
+                }
+            ]
+        );
+    }
+
+    fn input_text(input: &[i32]) -> String {
+        input
+            .iter()
+            .map(i32::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    proptest! {
+        #[test]
+        fn window_increases_match_naive(
+            input in prop::collection::vec(0..10_000_i32, 0..200),
+            window in 1..20_usize,
+        ) {
+            let expected = naive_increases(&input, window);
+            prop_assert_eq!(count_window_increases(&input, window), expected);
+            prop_assert_eq!(
+                count_window_increases_streaming(input_text(&input).as_bytes(), window)
+                    .unwrap_or(0),
+                expected
+            );
+        }
+
+        #[test]
+        fn part1_matches_naive(input in prop::collection::vec(0..10_000_i32, 0..200)) {
+            prop_assert_eq!(part1(&input), Ok(naive_increases(&input, 1)));
+        }
+
+        #[test]
+        fn part2_matches_naive(input in prop::collection::vec(0..10_000_i32, 0..200)) {
+            prop_assert_eq!(part2(&input), Ok(naive_increases(&input, 3)));
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    proptest! {
+        #[test]
+        fn nightly_matches_portable(input in prop::collection::vec(0..10_000_i32, 0..200)) {
+            prop_assert_eq!(count_increases::<2>(&input), portable_count_increases::<2>(&input));
+            prop_assert_eq!(count_increases::<4>(&input), portable_count_increases::<4>(&input));
+        }
+
+        #[test]
+        fn simd_matches_scalar(
+            input in prop::collection::vec(any::<i32>(), 0..500),
+            window in 0..40_usize,
+        ) {
+            prop_assert_eq!(
+                simd_count_window_increases(&input, window),
+                count_window_increases(&input, window)
+            );
+        }
+    }
+}
@@ -1,41 +1,456 @@
-use std::error::Error;
+use std::{
+    fmt::{self, Write},
+    io::BufRead,
+    str::FromStr,
+};
 
-use aoc_runner_derive::aoc;
+use aoc_runner_derive::{aoc, aoc_generator};
 
-#[aoc(day2, part1)]
-fn part1(input: &str) -> Result<i32, Box<dyn Error>> {
-    let mut depth = 0;
-    let mut pos = 0;
-    for s in input.split('\n') {
-        let (cmd, val) = s.split_once(' ').unwrap_or_default();
-        let val = val.parse::<i32>()?;
-        match cmd {
-            "up" => depth -= val,
-            "down" => depth += val,
-            "forward" => pos += val,
-            _ => {}
+use crate::{
+    error::{self, parse_field, Error},
+    streaming::for_each_line,
+};
+
+pub mod inverse;
+mod script;
+
+use script::ScriptParser;
+
+/// Most commands the in-memory generator will expand a script into.
+const MAX_COMMANDS: usize = 1 << 24;
+
+/// A single step of the planned course.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Forward(i32),
+    Down(i32),
+    Up(i32),
+}
+
+impl Command {
+    /// Parses a `<verb> <value>` line, reporting errors at `line_no` (1-based).
+    pub fn parse(line_no: usize, line: &str) -> error::Result<Command> {
+        Command::parse_in(line_no, line, line)
+    }
+
+    /// Parses `text`, a subslice of `line`, so that errors point at the right column.
+    fn parse_in(line_no: usize, line: &str, text: &str) -> error::Result<Command> {
+        let (verb, val) = text
+            .split_once(' ')
+            .ok_or_else(|| Error::at(line_no, line, text, "expected `<command> <value>`"))?;
+        let command = match verb {
+            "forward" => Command::Forward,
+            "down" => Command::Down,
+            "up" => Command::Up,
+            _ => {
+                let message =
+                    format!("unknown command `{verb}`, expected `forward`, `down` or `up`");
+                return Err(Error::at(line_no, line, verb, message));
+            }
+        };
+        Ok(command(parse_field(line_no, line, val)?))
+    }
+}
+
+/// Formats the command as a line of puzzle input.
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Forward(val) => write!(f, "forward {val}"),
+            Command::Down(val) => write!(f, "down {val}"),
+            Command::Up(val) => write!(f, "up {val}"),
+        }
+    }
+}
+
+/// Parses a single command, as if it were the first line of the input.
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(s: &str) -> error::Result<Command> {
+        Command::parse(1, s)
+    }
+}
+
+/// Parses the planned course, expanding any script constructs (see [`script`]).  Fails if the
+/// script expands to more than `MAX_COMMANDS` commands, or any statement takes too many steps to
+/// run.
+#[aoc_generator(day2)]
+pub fn generator(input: &str) -> error::Result<Vec<Command>> {
+    let mut parser = ScriptParser::default();
+    let mut commands = vec![];
+    for (i, line) in input.lines().enumerate() {
+        parser.line(i + 1, line, &mut |command| {
+            if commands.len() == MAX_COMMANDS {
+                let message = format!("script expands to more than {MAX_COMMANDS} commands");
+                return Err(Error::at(i + 1, line, line, message));
+            }
+            commands.push(command);
+            Ok(())
+        })?;
+    }
+    parser.finish()?;
+    if commands.is_empty() {
+        return Err(Error::EmptyInput);
+    }
+    Ok(commands)
+}
+
+/// Where the submarine is.  Depth increases downwards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Submarine {
+    pub position: i64,
+    pub depth: i64,
+    pub aim: i64,
+}
+
+impl Submarine {
+    /// The puzzle answer: horizontal position multiplied by depth.  Widened so it can't overflow.
+    pub fn answer(&self) -> i128 {
+        i128::from(self.position) * i128::from(self.depth)
+    }
+}
+
+/// `a + b`, or an overflow error.
+fn add(a: i64, b: impl Into<i64>) -> error::Result<i64> {
+    a.checked_add(b.into()).ok_or(Error::Overflow)
+}
+
+/// `a * b`, or an overflow error.
+fn mul(a: i64, b: impl Into<i64>) -> error::Result<i64> {
+    a.checked_mul(b.into()).ok_or(Error::Overflow)
+}
+
+/// How a command changes the submarine's state.  Models report `Error::Overflow` rather than
+/// wrapping when the state gets too large.
+pub trait MovementModel {
+    fn apply(&self, sub: &mut Submarine, command: Command) -> error::Result<()>;
+
+    /// Follows `commands` from the surface, returning where the submarine ends up.
+    fn navigate(&self, commands: impl IntoIterator<Item = Command>) -> error::Result<Submarine>
+    where
+        Self: Sized,
+    {
+        let mut sub = Submarine::default();
+        for command in commands {
+            self.apply(&mut sub, command)?;
         }
+        Ok(sub)
     }
-    Ok(depth * pos)
+
+    /// Follows `commands` from the surface, recording every state along the way.
+    fn trace(&self, commands: impl IntoIterator<Item = Command>) -> error::Result<Trajectory>
+    where
+        Self: Sized,
+    {
+        let mut sub = Submarine::default();
+        let mut states = vec![sub];
+        for command in commands {
+            self.apply(&mut sub, command)?;
+            states.push(sub);
+        }
+        Ok(Trajectory { states })
+    }
+}
+
+/// Every state of the submarine along a course: the surface, then the state after each command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trajectory {
+    pub states: Vec<Submarine>,
+}
+
+impl Trajectory {
+    /// One row per state, numbered by the count of commands followed so far.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("step,position,depth,aim\n");
+        for (step, sub) in self.states.iter().enumerate() {
+            writeln!(out, "{step},{},{},{}", sub.position, sub.depth, sub.aim).unwrap();
+        }
+        out
+    }
+
+    /// A plot of depth against position, with depth increasing downwards.
+    pub fn to_svg(&self) -> String {
+        const WIDTH: f64 = 800.0;
+        const HEIGHT: f64 = 400.0;
+        const MARGIN: f64 = 40.0;
+
+        let bounds = |f: fn(&Submarine) -> i64| {
+            let values = self.states.iter().map(f);
+            (values.clone().min().unwrap_or(0), values.max().unwrap_or(0))
+        };
+        let (min_pos, max_pos) = bounds(|s| s.position);
+        let (min_depth, max_depth) = bounds(|s| s.depth);
+        // scales a value to a fraction of the plot, placing a flat course at the start
+        let scale = |value: i64, min: i64, max: i64| {
+            (value as f64 - min as f64) / (max as f64 - min as f64).max(1.0)
+        };
+
+        let mut points = String::new();
+        for sub in &self.states {
+            let x = MARGIN + scale(sub.position, min_pos, max_pos) * (WIDTH - 2.0 * MARGIN);
+            let y = MARGIN + scale(sub.depth, min_depth, max_depth) * (HEIGHT - 2.0 * MARGIN);
+            write!(points, "{x:.1},{y:.1} ").unwrap();
+        }
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}">"#
+        )
+        .unwrap();
+        writeln!(out, r#"  <rect width="100%" height="100%" fill="white"/>"#).unwrap();
+        writeln!(
+            out,
+            r#"  <text x="{MARGIN}" y="{}" font-size="12">position {} to {}</text>"#,
+            HEIGHT - 10.0,
+            min_pos,
+            max_pos
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r#"  <text x="{MARGIN}" y="20" font-size="12">depth {} to {}</text>"#,
+            min_depth, max_depth
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r#"  <polyline fill="none" stroke="steelblue" stroke-width="1.5" points="{}"/>"#,
+            points.trim_end()
+        )
+        .unwrap();
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+/// Part 1: `up` and `down` change the depth directly.
+pub struct Direct;
+
+impl MovementModel for Direct {
+    fn apply(&self, sub: &mut Submarine, command: Command) -> error::Result<()> {
+        match command {
+            Command::Up(val) => sub.depth = add(sub.depth, -i64::from(val))?,
+            Command::Down(val) => sub.depth = add(sub.depth, val)?,
+            Command::Forward(val) => sub.position = add(sub.position, val)?,
+        }
+        Ok(())
+    }
+}
+
+/// Part 2: `up` and `down` change the aim, and moving forward dives along it.
+pub struct Aimed;
+
+impl MovementModel for Aimed {
+    fn apply(&self, sub: &mut Submarine, command: Command) -> error::Result<()> {
+        match command {
+            Command::Up(val) => sub.aim = add(sub.aim, -i64::from(val))?,
+            Command::Down(val) => sub.aim = add(sub.aim, val)?,
+            Command::Forward(val) => {
+                sub.position = add(sub.position, val)?;
+                sub.depth = add(sub.depth, mul(sub.aim, val)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Streaming version of `MovementModel::navigate`, running each command as soon as it has been
+/// read.  Unlike `generator`, there is no limit on how many commands a script expands to, only on
+/// the steps each statement takes to run.
+fn navigate_streaming(input: impl BufRead, model: &impl MovementModel) -> error::Result<Submarine> {
+    let mut parser = ScriptParser::default();
+    let mut sub = Submarine::default();
+    let mut commands = 0_usize;
+    for_each_line(input, |line_no, line| {
+        parser.line(line_no, line, &mut |command| {
+            commands += 1;
+            model.apply(&mut sub, command)
+        })
+    })?;
+    parser.finish()?;
+    if commands == 0 {
+        return Err(Error::EmptyInput);
+    }
+    Ok(sub)
+}
+
+pub(crate) fn part1_streaming(input: impl BufRead) -> error::Result<i128> {
+    Ok(navigate_streaming(input, &Direct)?.answer())
+}
+
+pub(crate) fn part2_streaming(input: impl BufRead) -> error::Result<i128> {
+    Ok(navigate_streaming(input, &Aimed)?.answer())
+}
+
+#[aoc(day2, part1)]
+fn part1(input: &[Command]) -> error::Result<i128> {
+    Ok(Direct.navigate(input.iter().copied())?.answer())
 }
 
 #[aoc(day2, part2)]
-fn part2(input: &str) -> Result<i32, Box<dyn Error>> {
-    let mut depth = 0;
-    let mut pos = 0;
-    let mut aim = 0;
-    for s in input.split('\n') {
-        let (cmd, val) = s.split_once(' ').unwrap_or_default();
-        let val = val.parse::<i32>()?;
-        match cmd {
-            "up" => aim -= val,
-            "down" => aim += val,
-            "forward" => {
-                pos += val;
-                depth += aim * val;
+fn part2(input: &[Command]) -> error::Result<i128> {
+    Ok(Aimed.navigate(input.iter().copied())?.answer())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &str = "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2";
+
+    #[test]
+    fn test_generator() {
+        assert_eq!(
+            generator("forward 5\n\ndown 5\nup 3\n"),
+            Ok(vec![Command::Forward(5), Command::Down(5), Command::Up(3)])
+        );
+        assert_eq!("up 7".parse(), Ok(Command::Up(7)));
+        assert_eq!(Command::Forward(12).to_string(), "forward 12");
+    }
+
+    #[test]
+    fn test_generator_errors() {
+        assert_eq!(generator("\n"), Err(Error::EmptyInput));
+        assert_eq!(
+            generator("forward 5\nsideways 2"),
+            Err(Error::Parse {
+                line: 2,
+                column: 1,
+                message: "unknown command `sideways`, expected `forward`, `down` or `up`".into()
+            })
+        );
+        assert!(matches!(
+            generator("forward 5\ndown"),
+            Err(Error::Parse {
+                line: 2,
+                column: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            generator("up x"),
+            Err(Error::Parse {
+                line: 1,
+                column: 4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_sample_data() {
+        let input = generator(SAMPLE).unwrap();
+        assert_eq!(part1(&input), Ok(150));
+        assert_eq!(part2(&input), Ok(900));
+        assert_eq!(part1_streaming(SAMPLE.as_bytes()), Ok(150));
+        assert_eq!(part2_streaming(SAMPLE.as_bytes()), Ok(900));
+    }
+
+    #[test]
+    fn trace_sample_data() {
+        let input = generator(SAMPLE).unwrap();
+        let trajectory = Aimed.trace(input.iter().copied()).unwrap();
+        assert_eq!(trajectory.states.len(), input.len() + 1);
+        assert_eq!(trajectory.states[0], Submarine::default());
+        assert_eq!(
+            trajectory.states.last(),
+            Some(&Aimed.navigate(input.iter().copied()).unwrap())
+        );
+
+        let csv = trajectory.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("step,position,depth,aim"));
+        assert_eq!(lines.nth(3), Some("3,13,40,5"));
+        assert_eq!(csv.lines().count(), input.len() + 2);
+
+        let svg = trajectory.to_svg();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        let points = svg.split("points=\"").nth(1).unwrap();
+        assert_eq!(points.split(' ').count(), trajectory.states.len());
+    }
+
+    #[test]
+    fn svg_flat_course() {
+        // the depth never changes, so it can't be scaled, but is still labelled as it is
+        let trajectory = Direct.trace([Command::Forward(5)]).unwrap();
+        let svg = trajectory.to_svg();
+        assert!(svg.contains(">position 0 to 5<"));
+        assert!(svg.contains(">depth 0 to 0<"));
+        assert!(svg.contains(r#"points="40.0,40.0 760.0,40.0""#));
+    }
+
+    #[test]
+    fn scripts() {
+        let script = "# the sample course\nmacro dive {\n  forward 5\n  down 5\n}\ndive\nforward 8\nup 3\ndown 8\nforward 2\n";
+        let input = generator(script).unwrap();
+        assert_eq!(input, generator(SAMPLE).unwrap());
+        assert_eq!(part2_streaming(script.as_bytes()), Ok(900));
+
+        let huge = "repeat 20000 {\nrepeat 1000 {\nforward 1\n}\n}";
+        assert!(matches!(generator(huge), Err(Error::Parse { line: 5, .. })));
+        assert_eq!(
+            part1_streaming("repeat 1000 {\nforward 1\n}".as_bytes()),
+            Ok(0)
+        );
+        assert_eq!(
+            generator("# nothing\nmacro m {\nup 1\n}"),
+            Err(Error::EmptyInput)
+        );
+    }
+
+    #[test]
+    fn large_answers() {
+        // the answer no longer fits in an i32, but the state does
+        let input = "down 2000000000\nforward 2000000000";
+        let expected = Ok(4_000_000_000_000_000_000);
+        assert_eq!(part1(&generator(input).unwrap()), expected);
+        assert_eq!(part1_streaming(input.as_bytes()), expected);
+    }
+
+    #[test]
+    fn overflow() {
+        // aim reaches 3 * i32::MAX, so a single step forward dives deeper than i64::MAX
+        let input = "down 2147483647\ndown 2147483647\ndown 2147483647\nforward 2147483647";
+        assert_eq!(part2(&generator(input).unwrap()), Err(Error::Overflow));
+        assert_eq!(part2_streaming(input.as_bytes()), Err(Error::Overflow));
+
+        // part 1 would need billions of commands to get this deep
+        let mut sub = Submarine {
+            depth: i64::MAX - 1,
+            ..Submarine::default()
+        };
+        assert_eq!(
+            Direct.apply(&mut sub, Command::Down(2)),
+            Err(Error::Overflow)
+        );
+        assert_eq!(Direct.apply(&mut sub, Command::Up(2)), Ok(()));
+    }
+
+    #[test]
+    fn custom_model() {
+        /// Like `Direct`, but the submarine can't rise above the surface.
+        struct Bounded;
+
+        impl MovementModel for Bounded {
+            fn apply(&self, sub: &mut Submarine, command: Command) -> error::Result<()> {
+                Direct.apply(sub, command)?;
+                sub.depth = sub.depth.max(0);
+                Ok(())
             }
-            _ => {}
         }
+
+        let input = generator("up 3\nforward 2\ndown 4").unwrap();
+        let sub = Bounded.navigate(input.iter().copied()).unwrap();
+        assert_eq!(
+            sub,
+            Submarine {
+                position: 2,
+                depth: 4,
+                aim: 0
+            }
+        );
+        assert_eq!(Direct.navigate(input).unwrap().depth, 1);
     }
-    Ok(depth * pos)
 }
@@ -0,0 +1,173 @@
+//! Shortest scripts that take the submarine from the surface to a given position and depth.
+//!
+//! Scripts only use positive values, as in the puzzle inputs, so no single command can change a
+//! coordinate by more than `i32::MAX`.
+
+use crate::error::{self, Error};
+
+use super::{Aimed, Command, Direct, MovementModel};
+
+/// A movement model that can be run backwards.
+pub trait Invertible: MovementModel {
+    /// Returns a script of as few commands as possible that ends at `position` and `depth`, or
+    /// `Error::NoSolution` if the model can't get there with positive values.
+    fn script_to(&self, position: i32, depth: i32) -> error::Result<Vec<Command>>;
+}
+
+/// Commands that add `change` to a coordinate, using `up` for negative changes.  Needs two
+/// commands only for `i32::MIN`.
+fn adjust(change: i32, down: fn(i32) -> Command, up: fn(i32) -> Command) -> Vec<Command> {
+    match change {
+        0 => vec![],
+        i32::MIN => vec![up(i32::MAX), up(1)],
+        _ if change > 0 => vec![down(change)],
+        _ => vec![up(-change)],
+    }
+}
+
+fn forward(position: i32) -> Vec<Command> {
+    adjust(position, Command::Forward, |_| {
+        unreachable!("position is never negative")
+    })
+}
+
+/// Part 1's model: each command moves one coordinate by its value, so one command per non-zero
+/// coordinate is needed, plus one more to reach a depth of `i32::MIN`.
+impl Invertible for Direct {
+    fn script_to(&self, position: i32, depth: i32) -> error::Result<Vec<Command>> {
+        if position < 0 {
+            return Err(Error::NoSolution);
+        }
+        let mut script = forward(position);
+        script.extend(adjust(depth, Command::Down, Command::Up));
+        Ok(script)
+    }
+}
+
+/// Part 2's model.  Depth only changes when moving forward, by the aim times the distance, so:
+///
+/// - the surface needs nothing, a depth of zero just the forward move, and any other depth at
+///   least one aim command as well;
+/// - two commands, aim then forward, reach depths divisible by the position;
+/// - three reach anything else: moving forward all but `y` first, then aiming at `depth / y` for
+///   the last `y`, where `y` is 1, or 2 for `i32::MIN` so that the aim fits in one command.
+///
+/// The only exception is aiming at `i32::MIN` for a single step forward, which needs two aim
+/// commands and so also takes three.
+impl Invertible for Aimed {
+    fn script_to(&self, position: i32, depth: i32) -> error::Result<Vec<Command>> {
+        if position < 0 || (position == 0 && depth != 0) {
+            return Err(Error::NoSolution);
+        }
+        if depth % position.max(1) == 0 {
+            let mut script = adjust(depth / position.max(1), Command::Down, Command::Up);
+            script.extend(forward(position));
+            return Ok(script);
+        }
+
+        // `position` doesn't divide `depth`, so it's at least 2, and at least 3 if `depth` is
+        // `i32::MIN`
+        let last = if depth == i32::MIN { 2 } else { 1 };
+        let mut script = forward(position - last);
+        script.extend(adjust(depth / last, Command::Down, Command::Up));
+        script.extend(forward(last));
+        Ok(script)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::day2::{part1, part2};
+    use proptest::prelude::*;
+
+    /// Runs `script` and checks it arrives at the target.
+    fn check(model: &impl Invertible, position: i32, depth: i32) -> Vec<Command> {
+        let script = model.script_to(position, depth).unwrap();
+        assert!(script.iter().all(|c| matches!(c,
+            Command::Forward(v) | Command::Down(v) | Command::Up(v) if *v > 0)));
+        let sub = model.navigate(script.iter().copied()).unwrap();
+        assert_eq!(
+            (sub.position, sub.depth),
+            (i64::from(position), i64::from(depth)),
+            "{script:?}"
+        );
+        script
+    }
+
+    #[test]
+    fn direct() {
+        assert_eq!(check(&Direct, 0, 0), vec![]);
+        assert_eq!(
+            check(&Direct, 15, -10),
+            vec![Command::Forward(15), Command::Up(10)]
+        );
+        assert_eq!(check(&Direct, i32::MAX, i32::MIN).len(), 3);
+        assert_eq!(part1(&check(&Direct, 15, 10)), Ok(150));
+        assert_eq!(Direct.script_to(-1, 0), Err(Error::NoSolution));
+    }
+
+    #[test]
+    fn aimed() {
+        assert_eq!(check(&Aimed, 0, 0), vec![]);
+        assert_eq!(check(&Aimed, 7, 0), vec![Command::Forward(7)]);
+        assert_eq!(
+            check(&Aimed, 15, 60),
+            vec![Command::Down(4), Command::Forward(15)]
+        );
+        assert_eq!(
+            check(&Aimed, 15, 61),
+            vec![Command::Forward(14), Command::Down(61), Command::Forward(1)]
+        );
+        assert_eq!(part2(&check(&Aimed, 15, 61)), Ok(15 * 61));
+        assert_eq!(check(&Aimed, 1, i32::MIN).len(), 3);
+        assert_eq!(check(&Aimed, 2, i32::MIN).len(), 2);
+        assert_eq!(check(&Aimed, 3, i32::MIN).len(), 3);
+        assert_eq!(check(&Aimed, i32::MAX, i32::MAX).len(), 2);
+        assert_eq!(Aimed.script_to(0, 5), Err(Error::NoSolution));
+        assert_eq!(Aimed.script_to(-3, 0), Err(Error::NoSolution));
+    }
+
+    /// Every script of up to two commands with values up to `max`.
+    fn short_scripts(max: i32) -> Vec<Vec<Command>> {
+        let commands = (1..=max)
+            .flat_map(|v| [Command::Forward(v), Command::Down(v), Command::Up(v)])
+            .collect::<Vec<_>>();
+        let mut scripts = vec![vec![]];
+        scripts.extend(commands.iter().map(|&c| vec![c]));
+        for &a in &commands {
+            scripts.extend(commands.iter().map(|&b| vec![a, b]));
+        }
+        scripts
+    }
+
+    #[test]
+    fn aimed_is_minimal() {
+        // the solver never needs more than three commands, so it's minimal if no script of two or
+        // fewer commands reaches a target it needed three for
+        let shorter = short_scripts(20)
+            .into_iter()
+            .map(|s| Aimed.navigate(s).unwrap())
+            .map(|sub| (sub.position, sub.depth))
+            .collect::<std::collections::HashSet<_>>();
+        for position in 0..=20 {
+            for depth in -20..=20 {
+                let Ok(script) = Aimed.script_to(position, depth) else {
+                    continue;
+                };
+                let target = (i64::from(position), i64::from(depth));
+                assert_eq!(script.len() <= 2, shorter.contains(&target), "{target:?}");
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn scripts_reach_target(position in 0..=i32::MAX, depth in any::<i32>()) {
+            prop_assert!(check(&Direct, position, depth).len() <= 3);
+            if position > 0 || depth == 0 {
+                prop_assert!(check(&Aimed, position, depth).len() <= 3);
+            }
+        }
+    }
+}
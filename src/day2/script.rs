@@ -0,0 +1,368 @@
+//! Command scripts: the puzzle's `<verb> <value>` lines, plus comments, blank lines, `repeat`
+//! blocks and macros.
+//!
+//! ```text
+//! # dive in steps
+//! macro step {
+//!     forward 2
+//!     down 1
+//! }
+//! repeat 3 {
+//!     step
+//! }
+//! forward 5  # and level out
+//! ```
+//!
+//! Blocks open with a `{` at the end of their first line and close with a `}` on a line of its
+//! own.  Macros must be defined at the top level, before they are used, so they can't recurse.
+//! Each top-level statement may take at most `MAX_STEPS` steps to run, so that scripts can't run
+//! for ever, even if their blocks expand to few or no commands.
+
+use std::collections::HashMap;
+
+use crate::error::{self, parse_field, Error};
+
+use super::Command;
+
+/// Most steps a top-level statement may take to run.  Running any statement takes a step, and so
+/// does each pass through a `repeat` block.
+const MAX_STEPS: usize = 1 << 26;
+
+enum Statement {
+    Command(Command),
+    Repeat(usize, Vec<Statement>),
+    /// Runs the macro with this index in `ScriptParser::macros`.
+    Call(usize),
+}
+
+enum BlockKind {
+    Repeat(usize),
+    Macro(String),
+}
+
+/// A block whose closing `}` hasn't been seen yet.
+struct Block {
+    line_no: usize,
+    kind: BlockKind,
+    body: Vec<Statement>,
+}
+
+/// Parses a script a line at a time.  Each top-level statement is expanded into commands as soon
+/// as it is complete, so only macros and the block being parsed are kept in memory.
+pub(crate) struct ScriptParser {
+    names: HashMap<String, usize>,
+    macros: Vec<Vec<Statement>>,
+    open: Vec<Block>,
+    /// Most steps a top-level statement may take, `MAX_STEPS` except in tests.
+    max_steps: usize,
+}
+
+impl Default for ScriptParser {
+    fn default() -> ScriptParser {
+        ScriptParser {
+            names: HashMap::new(),
+            macros: vec![],
+            open: vec![],
+            max_steps: MAX_STEPS,
+        }
+    }
+}
+
+impl ScriptParser {
+    /// Parses `line`, which is line `line_no` (1-based) of the script, and passes any commands it
+    /// completes to `f`.
+    pub(crate) fn line(
+        &mut self,
+        line_no: usize,
+        line: &str,
+        f: &mut impl FnMut(Command) -> error::Result<()>,
+    ) -> error::Result<()> {
+        let code = line.split('#').next().unwrap_or_default().trim();
+        if code.is_empty() {
+            return Ok(());
+        }
+
+        if code == "}" {
+            let block = self
+                .open
+                .pop()
+                .ok_or_else(|| Error::at(line_no, line, code, "`}` without a matching `{`"))?;
+            return match block.kind {
+                BlockKind::Repeat(count) => {
+                    self.push(block.line_no, Statement::Repeat(count, block.body), f)
+                }
+                BlockKind::Macro(name) => {
+                    self.names.insert(name, self.macros.len());
+                    self.macros.push(block.body);
+                    Ok(())
+                }
+            };
+        }
+
+        if let Some(header) = code.strip_suffix('{') {
+            let kind = self.header(line_no, line, header.trim())?;
+            self.open.push(Block {
+                line_no,
+                kind,
+                body: vec![],
+            });
+            return Ok(());
+        }
+
+        if !code.contains(' ') {
+            let Some(&index) = self.names.get(code) else {
+                let message = match code {
+                    "forward" | "down" | "up" => "expected `<command> <value>`".to_string(),
+                    _ => format!("unknown macro `{code}`"),
+                };
+                return Err(Error::at(line_no, line, code, message));
+            };
+            return self.push(line_no, Statement::Call(index), f);
+        }
+
+        let command = Command::parse_in(line_no, line, code)?;
+        self.push(line_no, Statement::Command(command), f)
+    }
+
+    /// Checks that every block has been closed.
+    pub(crate) fn finish(self) -> error::Result<()> {
+        match self.open.last() {
+            Some(block) => Err(Error::Parse {
+                line: block.line_no,
+                column: 1,
+                message: "block is never closed".into(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Parses the first line of a block, without its `{`.
+    fn header(&self, line_no: usize, line: &str, header: &str) -> error::Result<BlockKind> {
+        match header.split_once(' ') {
+            Some(("repeat", count)) => {
+                Ok(BlockKind::Repeat(parse_field(line_no, line, count.trim())?))
+            }
+            Some(("macro", name)) => {
+                let name = name.trim();
+                let problem = if !self.open.is_empty() {
+                    Some("macros can only be defined at the top level".to_string())
+                } else if name.is_empty() || name.contains(char::is_whitespace) {
+                    Some("macro names must be a single word".to_string())
+                } else if matches!(name, "forward" | "down" | "up" | "repeat" | "macro") {
+                    Some(format!("`{name}` is reserved"))
+                } else if self.names.contains_key(name) {
+                    Some(format!("macro `{name}` is already defined"))
+                } else {
+                    None
+                };
+                match problem {
+                    Some(message) => Err(Error::at(line_no, line, name, message)),
+                    None => Ok(BlockKind::Macro(name.to_string())),
+                }
+            }
+            _ => Err(Error::at(
+                line_no,
+                line,
+                header,
+                "expected `repeat <count> {` or `macro <name> {`",
+            )),
+        }
+    }
+
+    /// Adds a complete statement, which starts on line `line_no`, to the innermost open block, or
+    /// runs it if there is none.
+    fn push(
+        &mut self,
+        line_no: usize,
+        statement: Statement,
+        f: &mut impl FnMut(Command) -> error::Result<()>,
+    ) -> error::Result<()> {
+        match self.open.last_mut() {
+            Some(block) => {
+                block.body.push(statement);
+                Ok(())
+            }
+            None => self.run(line_no, &statement, &mut 0, f),
+        }
+    }
+
+    /// Runs `statement`, part of the top-level statement on line `line_no` that has taken `steps`
+    /// steps so far.
+    fn run(
+        &self,
+        line_no: usize,
+        statement: &Statement,
+        steps: &mut usize,
+        f: &mut impl FnMut(Command) -> error::Result<()>,
+    ) -> error::Result<()> {
+        self.step(line_no, steps)?;
+        match statement {
+            Statement::Command(command) => f(*command),
+            Statement::Repeat(count, body) => {
+                for _ in 0..*count {
+                    self.step(line_no, steps)?;
+                    body.iter()
+                        .try_for_each(|s| self.run(line_no, s, steps, f))?;
+                }
+                Ok(())
+            }
+            Statement::Call(index) => self.macros[*index]
+                .iter()
+                .try_for_each(|s| self.run(line_no, s, steps, f)),
+        }
+    }
+
+    /// Counts a step of the top-level statement on line `line_no`, failing if it has taken too
+    /// many.
+    fn step(&self, line_no: usize, steps: &mut usize) -> error::Result<()> {
+        *steps += 1;
+        if *steps > self.max_steps {
+            return Err(Error::Parse {
+                line: line_no,
+                column: 1,
+                message: format!("statement takes more than {} steps to run", self.max_steps),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn expand(script: &str) -> error::Result<Vec<Command>> {
+        expand_with(ScriptParser::default(), script)
+    }
+
+    fn expand_with(mut parser: ScriptParser, script: &str) -> error::Result<Vec<Command>> {
+        let mut commands = vec![];
+        for (i, line) in script.lines().enumerate() {
+            parser.line(i + 1, line, &mut |c| {
+                commands.push(c);
+                Ok(())
+            })?;
+        }
+        parser.finish()?;
+        Ok(commands)
+    }
+
+    #[test]
+    fn expands_blocks_and_macros() {
+        let script = "\
+# dive in steps
+macro step {
+    forward 2
+    down 1  # a little
+}
+
+repeat 2 {
+    step
+    repeat 2 {
+        up 1
+    }
+}
+macro twice {
+    step
+    step
+}
+twice
+forward 5";
+        use Command::*;
+        assert_eq!(
+            expand(script),
+            Ok(vec![
+                Forward(2),
+                Down(1),
+                Up(1),
+                Up(1),
+                Forward(2),
+                Down(1),
+                Up(1),
+                Up(1),
+                Forward(2),
+                Down(1),
+                Forward(2),
+                Down(1),
+                Forward(5),
+            ])
+        );
+        assert_eq!(expand("repeat 0 {\nforward 1\n}"), Ok(vec![]));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |script| match expand(script) {
+            Err(Error::Parse {
+                line,
+                column,
+                message,
+            }) => (line, column, message),
+            other => panic!("expected a parse error, got {other:?}"),
+        };
+        assert_eq!(
+            error("forward 1\n  sideways 2"),
+            (
+                2,
+                3,
+                "unknown command `sideways`, expected `forward`, `down` or `up`".into()
+            )
+        );
+        assert_eq!(error("up 1\n  step"), (2, 3, "unknown macro `step`".into()));
+        assert_eq!(
+            error("repeat 2 {\nup 1\n"),
+            (1, 1, "block is never closed".into())
+        );
+        assert_eq!(
+            error("up 1\n}"),
+            (2, 1, "`}` without a matching `{`".into())
+        );
+        assert_eq!(error("repeat x {\n}").0, 1);
+        assert_eq!(
+            error("loop {\n}").2,
+            "expected `repeat <count> {` or `macro <name> {`"
+        );
+        assert_eq!(
+            error("repeat 2 {\nmacro m {\n}\n}"),
+            (2, 7, "macros can only be defined at the top level".into())
+        );
+        assert_eq!(
+            error("macro m {\n}\nmacro m {\n}"),
+            (3, 7, "macro `m` is already defined".into())
+        );
+        assert_eq!(error("macro up {\n}").2, "`up` is reserved");
+        // a macro isn't defined until it is closed, so it can't call itself
+        assert_eq!(error("macro m {\nm\n}").2, "unknown macro `m`");
+    }
+
+    #[test]
+    fn step_limit() {
+        let expand = |script: &str| {
+            let parser = ScriptParser {
+                max_steps: 100,
+                ..ScriptParser::default()
+            };
+            expand_with(parser, script)
+        };
+        let too_long = |line| {
+            Err(Error::Parse {
+                line,
+                column: 1,
+                message: "statement takes more than 100 steps to run".into(),
+            })
+        };
+        // blocks that expand to nothing still take steps
+        let script = format!("forward 1\nrepeat {} {{\n}}\ndown 1", usize::MAX);
+        assert_eq!(expand(&script), too_long(2));
+        assert_eq!(
+            expand("macro nothing {\n}\nrepeat 60 {\n    nothing\n}"),
+            too_long(3)
+        );
+        // a repeat takes a step per pass, and one per statement run
+        assert_eq!(expand("repeat 49 {\n    up 1\n}").map(|c| c.len()), Ok(49));
+        assert_eq!(expand("repeat 50 {\n    up 1\n}"), too_long(1));
+        // the limit is per top-level statement
+        let script = "repeat 90 {\n}\nrepeat 90 {\n}\nup 1";
+        assert_eq!(expand(script), Ok(vec![Command::Up(1)]));
+    }
+}
@@ -1,153 +1,1048 @@
-use std::collections::HashSet;
+use std::{fmt, io::BufRead};
 
 use aoc_runner_derive::*;
+use rayon::prelude::*;
+use serde::{Serialize, Serializer};
 
+use crate::{
+    error::{self, Error},
+    streaming::for_each_line,
+};
+
+/// Bits packed into each word of a reading.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A diagnostic report: readings of `num_bits` bits each.
 #[derive(PartialEq, Eq, Debug)]
-struct Problem {
+pub struct Problem {
     pub num_bits: usize,
-    pub data: Vec<u32>,
+    /// The readings, one after another, each packed into `words()` words.  Bits are stored most
+    /// significant first, starting at the top of the first word, and the unused bits at the end
+    /// of the last word are zero.  Comparing two readings' words therefore compares the readings.
+    pub data: Vec<u64>,
+}
+
+impl Problem {
+    /// Number of words used by each reading.
+    fn words(&self) -> usize {
+        self.num_bits.div_ceil(WORD_BITS)
+    }
+
+    fn readings(&self) -> impl ExactSizeIterator<Item = &[u64]> {
+        self.data.chunks_exact(self.words())
+    }
+
+    fn par_readings(&self) -> impl IndexedParallelIterator<Item = &[u64]> {
+        self.data.par_chunks_exact(self.words())
+    }
+
+    fn reading(&self, index: usize) -> Reading {
+        let words = self.words();
+        Reading {
+            num_bits: self.num_bits,
+            words: self.data[index * words..(index + 1) * words].to_vec(),
+        }
+    }
+}
+
+/// A reading, or a rate built from the readings, of any width.  Displays and serializes as a
+/// string of `0`s and `1`s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reading {
+    num_bits: usize,
+    /// Packed the same way as `Problem::data`.
+    words: Vec<u64>,
 }
 
+impl Reading {
+    fn from_bits(bits: impl ExactSizeIterator<Item = bool>) -> Reading {
+        let num_bits = bits.len();
+        let mut words = vec![0; num_bits.div_ceil(WORD_BITS)];
+        for (column, b) in bits.enumerate() {
+            words[column / WORD_BITS] |= u64::from(b) << (WORD_BITS - 1 - column % WORD_BITS);
+        }
+        Reading { num_bits, words }
+    }
+
+    /// Returns bit `column`, counting from the most significant bit.
+    pub fn bit(&self, column: usize) -> bool {
+        bit(&self.words, column)
+    }
+
+    /// The bits, most significant first.
+    pub fn bits(&self) -> impl ExactSizeIterator<Item = bool> + Clone + '_ {
+        (0..self.num_bits).map(|column| self.bit(column))
+    }
+
+    /// The reading as a number, or `Error::Overflow` if it doesn't fit in a `u128`.
+    pub fn value(&self) -> error::Result<u128> {
+        value(self.bits())
+    }
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.bits()
+            .try_for_each(|b| f.write_str(if b { "1" } else { "0" }))
+    }
+}
+
+impl Serialize for Reading {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Returns bit `column` of `reading`, counting from the most significant bit.
+fn bit(reading: &[u64], column: usize) -> bool {
+    reading[column / WORD_BITS] >> (WORD_BITS - 1 - column % WORD_BITS) & 1 == 1
+}
+
+/// Converts bits, most significant first, to a number.
+fn value(bits: impl IntoIterator<Item = bool>) -> error::Result<u128> {
+    bits.into_iter().try_fold(0_u128, |value, bit| {
+        if value.leading_zeros() == 0 {
+            return Err(Error::Overflow);
+        }
+        Ok(value << 1 | u128::from(bit))
+    })
+}
+
+/// Counts the ones in each of the first `columns` columns of `readings`, in a single pass split
+/// across threads.  Each thread adds up its share of the readings in its own vector, and the
+/// vectors are summed at the end.
+fn count_ones<'a>(readings: impl ParallelIterator<Item = &'a [u64]>, columns: usize) -> Vec<usize> {
+    readings
+        .fold(
+            || vec![0; columns],
+            |mut ones, reading| {
+                for (counts, &word) in ones.chunks_mut(WORD_BITS).zip(reading) {
+                    for (count, shift) in counts.iter_mut().zip((0..WORD_BITS).rev()) {
+                        *count += (word >> shift & 1) as usize;
+                    }
+                }
+                ones
+            },
+        )
+        .reduce(
+            || vec![0; columns],
+            |mut ones, other| {
+                for (count, other) in ones.iter_mut().zip(other) {
+                    *count += other;
+                }
+                ones
+            },
+        )
+}
+
+/// Multiplies two rates or ratings, which is how both parts' answers are found.  Answers are
+/// returned as `u128`, so this fails with `Error::AnswerTooLarge` if either value or their product
+/// needs more bits.  That can't happen for readings of up to 64 bits, and always happens in part 1
+/// for readings wider than 128, as either gamma or epsilon has a one in the top bit.
+fn product(
+    a: impl IntoIterator<Item = bool>,
+    b: impl IntoIterator<Item = bool>,
+) -> error::Result<u128> {
+    let too_large = |_| Error::AnswerTooLarge;
+    let (a, b) = (value(a).map_err(too_large)?, value(b).map_err(too_large)?);
+    a.checked_mul(b).ok_or(Error::AnswerTooLarge)
+}
+
+/// Checks that `line` is a reading of `num_bits` bits, reporting problems at `line_no` (1-based).
+fn validate_reading(line_no: usize, line: &str, num_bits: usize) -> error::Result<()> {
+    if line.len() != num_bits {
+        return Err(Error::InconsistentWidth {
+            line: line_no,
+            expected: num_bits,
+            found: line.len(),
+        });
+    }
+    match line.bytes().position(|c| c != b'0' && c != b'1') {
+        Some(j) => Err(Error::at(line_no, line, &line[j..], "expected `0` or `1`")),
+        None => Ok(()),
+    }
+}
+
+/// Every line must be as wide as the first.  Readings can be any width, although the answers to
+/// reports wider than 64 bits may be too large to return (see `product`).
 #[aoc_generator(day3)]
-fn generator(input: &str) -> Problem {
-    let mut num_bits = 0;
-    let data = input
-        .split('\n')
-        .map(|n| {
-            if num_bits == 0 {
-                num_bits = n.len();
-            }
-            let mut x = 0;
-            for c in n.bytes() {
-                x <<= 1;
-                x |= (c == b'1') as u32;
-            }
-            x
+pub fn generator(input: &str) -> error::Result<Problem> {
+    let num_bits = input.lines().next().ok_or(Error::EmptyInput)?.len();
+    if num_bits == 0 {
+        return Err(Error::EmptyInput);
+    }
+
+    let mut data = vec![];
+    for (i, line) in input.lines().enumerate() {
+        validate_reading(i + 1, line, num_bits)?;
+        for chunk in line.as_bytes().chunks(WORD_BITS) {
+            let word = chunk
+                .iter()
+                .fold(0, |word, &c| word << 1 | u64::from(c == b'1'));
+            data.push(word << (WORD_BITS - chunk.len()));
+        }
+    }
+
+    Ok(Problem { num_bits, data })
+}
+
+/// One step of filtering the readings for a rating.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Elimination {
+    /// The bit considered, counting from the most significant.
+    pub column: usize,
+    /// Counts of ones and zeros in `column` among the readings left before this step.
+    pub ones: usize,
+    pub zeros: usize,
+    /// The bit value readings needed in `column` to be kept.
+    pub kept: bool,
+    /// Readings left after this step.
+    pub remaining: usize,
+}
+
+/// Which bit value a rating keeps in each column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Criterion {
+    MostCommon,
+    LeastCommon,
+}
+
+/// What a rating keeps in a column with as many ones as zeros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tie {
+    Zero,
+    One,
+    /// Stop filtering, leaving the rating undetermined.
+    Error,
+}
+
+/// The order in which a rating considers the columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// How a rating filters the readings: which columns it looks at first, and which bit value it
+/// keeps in each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BitCriteria {
+    pub criterion: Criterion,
+    pub tie: Tie,
+    pub order: ScanOrder,
+}
+
+impl BitCriteria {
+    /// The puzzle's oxygen generator rating: the most common bit, with ones winning ties.
+    pub const OXYGEN_GENERATOR: BitCriteria = BitCriteria {
+        criterion: Criterion::MostCommon,
+        tie: Tie::One,
+        order: ScanOrder::MsbFirst,
+    };
+
+    /// The puzzle's CO2 scrubber rating: the least common bit, with zeros winning ties.
+    pub const CO2_SCRUBBER: BitCriteria = BitCriteria {
+        criterion: Criterion::LeastCommon,
+        tie: Tie::Zero,
+        order: ScanOrder::MsbFirst,
+    };
+
+    /// The bit value kept in a column of `ones` ones and `zeros` zeros, or `None` if they are
+    /// tied and ties are errors.
+    pub fn keep(&self, ones: usize, zeros: usize) -> Option<bool> {
+        if ones == zeros {
+            return match self.tie {
+                Tie::Zero => Some(false),
+                Tie::One => Some(true),
+                Tie::Error => None,
+            };
+        }
+        Some((ones > zeros) == (self.criterion == Criterion::MostCommon))
+    }
+
+    /// The columns of readings `num_bits` wide, in the order they are considered.
+    fn columns(&self, num_bits: usize) -> impl ExactSizeIterator<Item = usize> {
+        let order = self.order;
+        (0..num_bits).map(move |step| match order {
+            ScanOrder::MsbFirst => step,
+            ScanOrder::LsbFirst => num_bits - 1 - step,
         })
-        .collect::<Vec<u32>>();
+    }
+}
 
-    Problem { num_bits, data }
+impl fmt::Display for BitCriteria {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let criterion = match self.criterion {
+            Criterion::MostCommon => "most common bit",
+            Criterion::LeastCommon => "least common bit",
+        };
+        let tie = match self.tie {
+            Tie::Zero => "ties keep 0",
+            Tie::One => "ties keep 1",
+            Tie::Error => "ties are errors",
+        };
+        let order = match self.order {
+            ScanOrder::MsbFirst => "most significant bit first",
+            ScanOrder::LsbFirst => "least significant bit first",
+        };
+        write!(f, "{criterion}, {tie}, {order}")
+    }
 }
 
-#[aoc(day3, part1)]
-fn part1(input: &Problem) -> i64 {
-    let mut gamma = 0;
-
-    for i in (0..input.num_bits).rev() {
-        let mask = 1 << i;
-        let mut ones = 0;
-        let mut zeros = 0;
-        for x in input.data.iter() {
-            if (x & mask) == 0 {
-                zeros += 1;
-            } else {
-                ones += 1;
+/// A life support rating, and how it was found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rating {
+    pub name: &'static str,
+    pub criteria: BitCriteria,
+    pub trace: Vec<Elimination>,
+    /// The column filtering stopped at because its bits were tied, if the criteria don't break
+    /// ties.
+    pub tied_column: Option<usize>,
+    /// The readings left when filtering stopped.  One, or several copies of it, if the rating was
+    /// determined.
+    pub candidates: Vec<Reading>,
+}
+
+impl Rating {
+    /// Filters the readings of `problem` one column at a time, keeping those whose bit matches
+    /// `criteria`, until the readings left are all the same, every column has been considered or
+    /// a column is tied and `criteria` make that an error.  Identical readings can't be told apart,
+    /// so any number of them count as one.
+    pub fn find(problem: &Problem, name: &'static str, criteria: BitCriteria) -> Rating {
+        let ones = count_ones(problem.par_readings(), problem.num_bits);
+        Rating::filter(problem, name, criteria, &ones)
+    }
+
+    /// Same as `find`, given the count of ones in each column of every reading, which saves
+    /// counting the first column considered.
+    fn filter(
+        problem: &Problem,
+        name: &'static str,
+        criteria: BitCriteria,
+        all_ones: &[usize],
+    ) -> Rating {
+        let mut remaining = problem.readings().enumerate().collect::<Vec<_>>();
+        let mut trace = vec![];
+        let mut tied_column = None;
+        for (step, column) in criteria.columns(problem.num_bits).enumerate() {
+            if remaining.windows(2).all(|pair| pair[0].1 == pair[1].1) {
+                break;
             }
+            let ones = match step {
+                0 => all_ones[column],
+                _ => remaining.iter().filter(|(_, r)| bit(r, column)).count(),
+            };
+            let zeros = remaining.len() - ones;
+            let Some(kept) = criteria.keep(ones, zeros) else {
+                tied_column = Some(column);
+                break;
+            };
+            remaining.retain(|(_, r)| bit(r, column) == kept);
+            trace.push(Elimination {
+                column,
+                ones,
+                zeros,
+                kept,
+                remaining: remaining.len(),
+            });
+        }
+        Rating {
+            name,
+            criteria,
+            trace,
+            tied_column,
+            candidates: remaining.iter().map(|&(i, _)| problem.reading(i)).collect(),
         }
-        let res = i64::from(ones > zeros);
-        gamma |= res << i;
     }
-    let epsilon = (!gamma) & ((1 << input.num_bits) - 1);
 
-    gamma * epsilon
+    /// The rating, or an error if filtering stopped at a tie or didn't leave a single reading,
+    /// possibly repeated.
+    pub fn reading(&self) -> error::Result<&Reading> {
+        if let Some(column) = self.tied_column {
+            return Err(Error::TiedBit {
+                rating: self.name.to_string(),
+                column,
+            });
+        }
+        match self.candidates.split_first() {
+            Some((reading, rest)) if rest.iter().all(|r| r == reading) => Ok(reading),
+            _ => Err(Error::UndeterminedRating {
+                rating: self.name.to_string(),
+                remaining: self.candidates.len(),
+            }),
+        }
+    }
 }
 
-#[aoc(day3, part2)]
-fn part2(input: &Problem) -> u32 {
-    let mut oxygen = None;
-    let mut co2 = None;
-    let mut o2_set: HashSet<u32> = HashSet::from_iter(input.data.iter().cloned());
-    let mut co2_set = o2_set.clone();
-
-    for i in (0..input.num_bits).rev() {
-        let mut ones = 0;
-        let mut zeros = 0;
-        let mask = 1 << i;
-
-        for x in o2_set.iter().cloned() {
-            if x & mask == 0 {
-                zeros += 1
-            } else {
-                ones += 1
-            }
+/// Everything the puzzle derives from a diagnostic report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticReport {
+    pub readings: usize,
+    /// Counts of ones and zeros in each column, most significant first.
+    pub ones: Vec<usize>,
+    pub zeros: Vec<usize>,
+    pub gamma: Reading,
+    pub epsilon: Reading,
+    pub oxygen: Rating,
+    pub co2: Rating,
+}
+
+impl DiagnosticReport {
+    pub fn new(problem: &Problem) -> DiagnosticReport {
+        let readings = problem.readings().len();
+        let ones = count_ones(problem.par_readings(), problem.num_bits);
+        let zeros = ones
+            .iter()
+            .map(|&count| readings - count)
+            .collect::<Vec<_>>();
+        let gamma = Reading::from_bits(ones.iter().zip(&zeros).map(|(o, z)| o > z));
+        let epsilon = Reading::from_bits(gamma.bits().map(|b| !b));
+        let oxygen = Rating::filter(
+            problem,
+            "oxygen generator",
+            BitCriteria::OXYGEN_GENERATOR,
+            &ones,
+        );
+        let co2 = Rating::filter(problem, "CO2 scrubber", BitCriteria::CO2_SCRUBBER, &ones);
+        DiagnosticReport {
+            readings,
+            ones,
+            zeros,
+            gamma,
+            epsilon,
+            oxygen,
+            co2,
         }
+    }
 
-        if ones >= zeros {
-            o2_set.retain(|x| x & mask != 0);
-        } else {
-            o2_set.retain(|x| x & mask == 0);
+    /// Gamma rate times epsilon rate: the answer to part 1.
+    pub fn power_consumption(&self) -> error::Result<u128> {
+        product(self.gamma.bits(), self.epsilon.bits())
+    }
+
+    /// Oxygen generator rating times CO2 scrubber rating: the answer to part 2.
+    pub fn life_support(&self) -> error::Result<u128> {
+        product(self.oxygen.reading()?.bits(), self.co2.reading()?.bits())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("reports always serialize")
+    }
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let answer = |answer: error::Result<u128>| match answer {
+            Ok(answer) => answer.to_string(),
+            Err(e) => e.to_string(),
         };
+        writeln!(f, "readings: {}", self.readings)?;
+        writeln!(f, "gamma:    {}", self.gamma)?;
+        writeln!(f, "epsilon:  {}", self.epsilon)?;
+        writeln!(f, "power consumption: {}", answer(self.power_consumption()))?;
 
-        if o2_set.len() == 1 {
-            oxygen = o2_set.iter().cloned().next();
-            break;
+        writeln!(f, "\n{:>6} {:>8} {:>8}", "bit", "ones", "zeros")?;
+        for (column, (ones, zeros)) in self.ones.iter().zip(&self.zeros).enumerate() {
+            writeln!(f, "{column:>6} {ones:>8} {zeros:>8}")?;
+        }
+
+        for rating in [&self.oxygen, &self.co2] {
+            writeln!(f, "\n{} rating ({}):", rating.name, rating.criteria)?;
+            writeln!(
+                f,
+                "{:>6} {:>8} {:>8} {:>5} {:>10}",
+                "bit", "ones", "zeros", "kept", "remaining"
+            )?;
+            for step in &rating.trace {
+                writeln!(
+                    f,
+                    "{:>6} {:>8} {:>8} {:>5} {:>10}",
+                    step.column,
+                    step.ones,
+                    step.zeros,
+                    u8::from(step.kept),
+                    step.remaining
+                )?;
+            }
+            match rating.reading() {
+                Ok(reading) => writeln!(f, "result: {reading}")?,
+                Err(e) => writeln!(f, "result: {e}")?,
+            }
         }
+        writeln!(f, "\nlife support: {}", answer(self.life_support()))
     }
+}
+
+/// Gamma rate times epsilon rate, given the count of ones in each column of `readings` readings.
+fn power_consumption(ones: &[usize], readings: usize) -> error::Result<u128> {
+    let gamma = ones.iter().map(|&count| count > readings - count);
+    product(gamma.clone(), gamma.map(|bit| !bit))
+}
 
-    for i in (0..input.num_bits).rev() {
-        let mut ones = 0;
-        let mut zeros = 0;
-        let mask = 1 << i;
-        for x in co2_set.iter().cloned() {
-            if x & mask == 0 {
-                zeros += 1
-            } else {
-                ones += 1
+/// Streaming version of `part1`, which only keeps a count of ones for each bit.
+pub(crate) fn part1_streaming(input: impl BufRead) -> error::Result<u128> {
+    let mut ones = Vec::new();
+    let lines = for_each_line(input, |line_no, line| {
+        // the first line sets the width, as in `generator`
+        if line_no == 1 {
+            if line.is_empty() {
+                return Err(Error::EmptyInput);
             }
+            ones = vec![0; line.len()];
         }
+        validate_reading(line_no, line, ones.len())?;
+        for (count, c) in ones.iter_mut().zip(line.bytes()) {
+            *count += (c == b'1') as usize;
+        }
+        Ok(())
+    })?;
+    if lines == 0 {
+        return Err(Error::EmptyInput);
+    }
 
-        if zeros <= ones {
-            co2_set.retain(|x| x & mask == 0);
-        } else {
-            co2_set.retain(|x| x & mask != 0);
-        };
+    power_consumption(&ones, lines)
+}
+
+#[aoc(day3, part1)]
+fn part1(input: &Problem) -> error::Result<u128> {
+    let ones = count_ones(input.par_readings(), input.num_bits);
+    power_consumption(&ones, input.readings().len())
+}
+
+#[aoc(day3, part2)]
+fn part2(input: &Problem) -> error::Result<u128> {
+    // both ratings start by counting a column of every reading, so count them all once
+    let ones = count_ones(input.par_readings(), input.num_bits);
+    let oxygen = Rating::filter(
+        input,
+        "oxygen generator",
+        BitCriteria::OXYGEN_GENERATOR,
+        &ones,
+    );
+    let co2 = Rating::filter(input, "CO2 scrubber", BitCriteria::CO2_SCRUBBER, &ones);
+    product(oxygen.reading()?.bits(), co2.reading()?.bits())
+}
+
+/// Sorts `readings` by their bits in the order `criteria` considers them, so that readings that
+/// share the first bits considered are next to each other.
+fn sort_for(readings: &mut [&[u64]], num_bits: usize, criteria: &BitCriteria) {
+    match criteria.order {
+        // readings are packed most significant bit first, so their words already compare that way
+        ScanOrder::MsbFirst => readings.sort_unstable(),
+        ScanOrder::LsbFirst => readings.sort_by_cached_key(|r| {
+            Reading::from_bits(criteria.columns(num_bits).map(|column| bit(r, column))).words
+        }),
+    }
+}
 
-        if co2_set.len() == 1 {
-            co2 = co2_set.iter().cloned().next();
+/// Finds a rating among `sorted`, readings sorted by `sort_for` for the same `criteria`.  The
+/// readings left after considering some bits all share them, so they are a range of `sorted`,
+/// and those with a zero in the next bit considered come first.
+fn sorted_rating<'a>(
+    sorted: &[&'a [u64]],
+    num_bits: usize,
+    name: &str,
+    criteria: BitCriteria,
+) -> error::Result<&'a [u64]> {
+    let mut range = sorted;
+    for column in criteria.columns(num_bits) {
+        // the range is sorted, so it only holds copies of one reading if its ends are the same
+        if range.first() == range.last() {
             break;
         }
+        let split = range.partition_point(|r| !bit(r, column));
+        let (zeros, ones) = range.split_at(split);
+        range = match criteria.keep(ones.len(), zeros.len()) {
+            Some(true) => ones,
+            Some(false) => zeros,
+            None => {
+                return Err(Error::TiedBit {
+                    rating: name.to_string(),
+                    column,
+                })
+            }
+        };
     }
+    // after every bit has been considered, whatever is left is copies of one reading
+    range
+        .first()
+        .copied()
+        .ok_or_else(|| Error::UndeterminedRating {
+            rating: name.to_string(),
+            remaining: 0,
+        })
+}
 
-    oxygen.unwrap() * co2.unwrap()
+/// Same as `part2`, but sorts the readings instead of filtering them for every bit, and then
+/// narrows down a range of them.
+#[aoc(day3, part2, sorted)]
+fn part2_sorted(input: &Problem) -> error::Result<u128> {
+    let mut sorted = input.readings().collect::<Vec<_>>();
+    let mut sorted_order = None;
+    let mut ratings = vec![];
+    for (name, criteria) in [
+        ("oxygen generator", BitCriteria::OXYGEN_GENERATOR),
+        ("CO2 scrubber", BitCriteria::CO2_SCRUBBER),
+    ] {
+        // both ratings consider the bits in the same order, so this only sorts once
+        if sorted_order != Some(criteria.order) {
+            sort_for(&mut sorted, input.num_bits, &criteria);
+            sorted_order = Some(criteria.order);
+        }
+        ratings.push(sorted_rating(&sorted, input.num_bits, name, criteria)?);
+    }
+    let bits = |r| (0..input.num_bits).map(move |column| bit(r, column));
+    product(bits(ratings[0]), bits(ratings[1]))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+
+    const SAMPLE: &str =
+        "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010";
+
+    /// Finds a rating by repeatedly filtering the readings, as described in the puzzle.  `keep`
+    /// decides which bit value survives given the counts of ones and zeros.  Copies of a reading
+    /// count as one number.  Returns `None` if every reading gets filtered out.
+    fn naive_rating(readings: &[String], keep: fn(usize, usize) -> u8) -> Option<String> {
+        let mut remaining = readings.to_vec();
+        let mut i = 0;
+        while remaining.iter().any(|r| *r != remaining[0]) {
+            let ones = remaining.iter().filter(|r| r.as_bytes()[i] == b'1').count();
+            let bit = keep(ones, remaining.len() - ones);
+            remaining.retain(|r| r.as_bytes()[i] == bit);
+            i += 1;
+        }
+        remaining.first().cloned()
+    }
+
+    /// Multiplies two binary numbers, or returns `Error::AnswerTooLarge` if they are too large.
+    fn naive_product(a: &str, b: &str) -> error::Result<u128> {
+        let parse = |s: &str| u128::from_str_radix(s, 2).map_err(|_| Error::AnswerTooLarge);
+        parse(a)?
+            .checked_mul(parse(b)?)
+            .ok_or(Error::AnswerTooLarge)
+    }
+
+    /// Readings of a common width, as strings of `0`s and `1`s, some of them repeated.  Some are
+    /// wider than any integer type.
+    fn readings() -> impl Strategy<Value = Vec<String>> {
+        prop_oneof![1..=12_usize, 60..=200_usize].prop_flat_map(|bits| {
+            let reading = prop::collection::vec(any::<bool>(), bits).prop_map(|bits| {
+                bits.iter()
+                    .map(|&b| if b { '1' } else { '0' })
+                    .collect::<String>()
+            });
+            let copies = prop::collection::vec(any::<prop::sample::Index>(), 0..10);
+            (prop::collection::vec(reading, 1..50), copies).prop_map(|(mut readings, copies)| {
+                for copy in copies {
+                    readings.push(copy.get(&readings).clone());
+                }
+                readings
+            })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn part1_matches_naive(readings in readings()) {
+            let bits = readings[0].len();
+            let column = |i| {
+                let ones = readings.iter().filter(|r: &&String| r.as_bytes()[i] == b'1').count();
+                ones > readings.len() - ones
+            };
+            let gamma = (0..bits).map(|i| if column(i) { '1' } else { '0' }).collect::<String>();
+            let epsilon = (0..bits).map(|i| if column(i) { '0' } else { '1' }).collect::<String>();
+
+            let input = readings.join("\n");
+            let problem = generator(&input).unwrap();
+            prop_assert_eq!(part1(&problem), naive_product(&gamma, &epsilon));
+            prop_assert_eq!(part1_streaming(input.as_bytes()), naive_product(&gamma, &epsilon));
+            // wide answers don't fit, so compare the rates themselves too
+            let report = DiagnosticReport::new(&problem);
+            prop_assert_eq!(report.gamma.to_string(), gamma);
+            prop_assert_eq!(report.epsilon.to_string(), epsilon);
+        }
+
+        #[test]
+        fn counts_match_sequential(readings in readings(), columns in 0..=200_usize) {
+            let problem = generator(&readings.join("\n")).unwrap();
+            let columns = columns.min(problem.num_bits);
+            let expected = (0..columns)
+                .map(|column| problem.readings().filter(|r| bit(r, column)).count())
+                .collect::<Vec<_>>();
+            prop_assert_eq!(count_ones(problem.par_readings(), columns), expected);
+        }
+
+        #[test]
+        fn part2_matches_naive(readings in readings()) {
+            let oxygen = naive_rating(&readings, |ones, zeros| if ones >= zeros { b'1' } else { b'0' });
+            let co2 = naive_rating(&readings, |ones, zeros| if zeros <= ones { b'0' } else { b'1' });
+            let undetermined = |rating: &str| Err(Error::UndeterminedRating {
+                rating: rating.to_string(),
+                remaining: 0,
+            });
+            let expected = match (&oxygen, &co2) {
+                (Some(oxygen), Some(co2)) => naive_product(oxygen, co2),
+                (None, _) => undetermined("oxygen generator"),
+                (_, None) => undetermined("CO2 scrubber"),
+            };
+
+            let problem = generator(&readings.join("\n")).unwrap();
+            let report = DiagnosticReport::new(&problem);
+            prop_assert_eq!(part2(&problem), expected.clone());
+            prop_assert_eq!(part2_sorted(&problem), expected.clone());
+            prop_assert_eq!(report.life_support(), expected);
+
+            // wide answers don't fit, so compare the ratings themselves too
+            let mut sorted = problem.readings().collect::<Vec<_>>();
+            sort_for(&mut sorted, problem.num_bits, &BitCriteria::OXYGEN_GENERATOR);
+            let to_string = |r: &[u64]| {
+                Reading { num_bits: problem.num_bits, words: r.to_vec() }.to_string()
+            };
+            for (rating, criteria, expected) in [
+                (&report.oxygen, BitCriteria::OXYGEN_GENERATOR, oxygen),
+                (&report.co2, BitCriteria::CO2_SCRUBBER, co2),
+            ] {
+                prop_assert_eq!(rating.reading().ok().map(Reading::to_string), expected.clone());
+                prop_assert_eq!(
+                    sorted_rating(&sorted, problem.num_bits, "test", criteria).ok().map(to_string),
+                    expected
+                );
+            }
+        }
+
+        #[test]
+        fn lsb_first_matches_reversed_readings(readings in readings()) {
+            let reversed = readings
+                .iter()
+                .map(|r| r.chars().rev().collect::<String>())
+                .collect::<Vec<_>>();
+            let problem = generator(&readings.join("\n")).unwrap();
+            let reversed_problem = generator(&reversed.join("\n")).unwrap();
+            for msb_first in [BitCriteria::OXYGEN_GENERATOR, BitCriteria::CO2_SCRUBBER] {
+                let lsb_first = BitCriteria { order: ScanOrder::LsbFirst, ..msb_first };
+                let candidates = |problem, criteria, reverse: bool| {
+                    Rating::find(problem, "test", criteria)
+                        .candidates
+                        .iter()
+                        .map(|r| {
+                            let bits = r.to_string();
+                            if reverse { bits.chars().rev().collect() } else { bits }
+                        })
+                        .collect::<Vec<String>>()
+                };
+                prop_assert_eq!(
+                    candidates(&problem, lsb_first, true),
+                    candidates(&reversed_problem, msb_first, false)
+                );
+
+                let mut sorted = problem.readings().collect::<Vec<_>>();
+                sort_for(&mut sorted, problem.num_bits, &lsb_first);
+                let rating = Rating::find(&problem, "test", lsb_first);
+                prop_assert_eq!(
+                    sorted_rating(&sorted, problem.num_bits, "test", lsb_first)
+                        .map(|r| Reading { num_bits: problem.num_bits, words: r.to_vec() }),
+                    rating.reading().cloned()
+                );
+            }
+        }
+
+        #[test]
+        fn report_matches_part1(readings in readings()) {
+            let problem = generator(&readings.join("\n")).unwrap();
+            prop_assert_eq!(DiagnosticReport::new(&problem).power_consumption(), part1(&problem));
+        }
+    }
+
     #[test]
     fn test_part1_sample_data() {
-        let data = vec![
-            0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
-            0b11001, 0b00010, 0b01010,
-        ];
-        let num_bits = 5;
-        let input = Problem { num_bits, data };
-        assert_eq!(part1(&input), 198);
+        let input = generator(SAMPLE).unwrap();
+        assert_eq!(part1(&input), Ok(198));
     }
 
     #[test]
     fn test_part2_sample_data() {
-        let data = vec![
-            0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
-            0b11001, 0b00010, 0b01010,
-        ];
-        let num_bits = 5;
-        let input = Problem { num_bits, data };
-        assert_eq!(part2(&input), 230);
+        let input = generator(SAMPLE).unwrap();
+        assert_eq!(part2(&input), Ok(230));
+        assert_eq!(part2_sorted(&input), Ok(230));
+
+        // each rating is four copies of the same reading
+        let input = generator(&[SAMPLE; 4].join("\n")).unwrap();
+        assert_eq!(part2(&input), Ok(230));
+        assert_eq!(part2_sorted(&input), Ok(230));
     }
 
     #[test]
-    fn test_generator() {
-        let input =
-            "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010";
+    fn report_sample_data() {
+        let report = DiagnosticReport::new(&generator(SAMPLE).unwrap());
+        assert_eq!(report.readings, 12);
+        assert_eq!(report.ones, [7, 5, 8, 7, 5]);
+        assert_eq!(report.zeros, [5, 7, 4, 5, 7]);
+        assert_eq!(report.gamma.to_string(), "10110");
+        assert_eq!(report.epsilon.value(), Ok(9));
+        assert_eq!(report.oxygen.reading().unwrap().to_string(), "10111");
+        assert_eq!(report.co2.reading().unwrap().value(), Ok(10));
+        assert_eq!(
+            report
+                .oxygen
+                .trace
+                .iter()
+                .map(|s| s.remaining)
+                .collect::<Vec<_>>(),
+            [7, 4, 3, 2, 1]
+        );
+        assert_eq!(
+            report.co2.trace[0],
+            Elimination {
+                column: 0,
+                ones: 7,
+                zeros: 5,
+                kept: false,
+                remaining: 5
+            }
+        );
+        assert_eq!(report.co2.trace.len(), 3);
+        assert_eq!(report.power_consumption(), Ok(198));
+        assert_eq!(report.life_support(), Ok(230));
 
-        let data = vec![
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["gamma"], "10110");
+        assert_eq!(json["oxygen"]["candidates"][0], "10111");
+        assert!(report.to_string().contains("life support: 230"));
+    }
+
+    #[test]
+    fn bit_criteria() {
+        let oxygen = BitCriteria::OXYGEN_GENERATOR;
+        let co2 = BitCriteria::CO2_SCRUBBER;
+        assert_eq!(
+            [oxygen.keep(3, 2), co2.keep(3, 2)],
+            [Some(true), Some(false)]
+        );
+        assert_eq!(
+            [oxygen.keep(2, 3), co2.keep(2, 3)],
+            [Some(false), Some(true)]
+        );
+        assert_eq!(
+            [oxygen.keep(2, 2), co2.keep(2, 2)],
+            [Some(true), Some(false)]
+        );
+        let strict = BitCriteria {
+            tie: Tie::Error,
+            ..oxygen
+        };
+        assert_eq!([strict.keep(3, 2), strict.keep(2, 2)], [Some(true), None]);
+
+        let lsb_first = BitCriteria {
+            order: ScanOrder::LsbFirst,
+            ..co2
+        };
+        assert_eq!(lsb_first.columns(3).collect::<Vec<_>>(), [2, 1, 0]);
+        assert_eq!(
+            lsb_first.to_string(),
+            "least common bit, ties keep 0, least significant bit first"
+        );
+    }
+
+    #[test]
+    fn custom_criteria() {
+        let problem = generator(SAMPLE).unwrap();
+        let lsb_first = BitCriteria {
+            order: ScanOrder::LsbFirst,
+            ..BitCriteria::OXYGEN_GENERATOR
+        };
+        let rating = Rating::find(&problem, "oxygen generator", lsb_first);
+        assert_eq!(rating.trace[0].column, 4);
+        assert_eq!(rating.reading().unwrap().to_string(), "11110");
+
+        // the first bit of the sample's last four readings is tied
+        let input = SAMPLE.lines().skip(8).collect::<Vec<_>>().join("\n");
+        let strict = BitCriteria {
+            tie: Tie::Error,
+            ..BitCriteria::OXYGEN_GENERATOR
+        };
+        let rating = Rating::find(&generator(&input).unwrap(), "oxygen generator", strict);
+        assert!(rating.trace.is_empty());
+        assert_eq!(
+            rating.reading(),
+            Err(Error::TiedBit {
+                rating: "oxygen generator".into(),
+                column: 0
+            })
+        );
+        assert_eq!(rating.candidates.len(), 4);
+    }
+
+    #[test]
+    fn undetermined_ratings() {
+        // copies of a reading are all the same rating
+        let report = DiagnosticReport::new(&generator("101\n101\n000").unwrap());
+        assert_eq!(report.oxygen.candidates.len(), 2);
+        assert_eq!(report.oxygen.reading().unwrap().to_string(), "101");
+        assert_eq!(report.life_support(), Ok(0));
+        // filtering stops as soon as only copies are left, before the least common bit of the
+        // second column would rule them all out
+        let report = DiagnosticReport::new(&generator("101\n101\n011").unwrap());
+        assert_eq!(report.co2.reading().unwrap().to_string(), "011");
+        assert_eq!(report.oxygen.trace.len(), 1);
+        assert_eq!(report.life_support(), Ok(5 * 3));
+        assert_eq!(
+            part2_sorted(&generator("101\n101\n011").unwrap()),
+            Ok(5 * 3)
+        );
+
+        // every reading has a one first, so none has the least common bit
+        let report = DiagnosticReport::new(&generator("110\n101").unwrap());
+        assert_eq!(report.oxygen.reading().unwrap().to_string(), "110");
+        assert_eq!(
+            report.co2.reading(),
+            Err(Error::UndeterminedRating {
+                rating: "CO2 scrubber".into(),
+                remaining: 0
+            })
+        );
+        assert!(report
+            .to_string()
+            .contains("no reading is left for the CO2 scrubber rating"));
+    }
+
+    #[test]
+    fn test_generator() {
+        let data = [
             0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
             0b11001, 0b00010, 0b01010,
         ];
         let num_bits = 5;
-        let problem = Problem { num_bits, data };
+        let problem = Problem {
+            num_bits,
+            data: data.iter().map(|x| x << (64 - num_bits)).collect(),
+        };
+
+        assert_eq!(Ok(problem), generator(SAMPLE));
+    }
+
+    #[test]
+    fn test_generator_errors() {
+        assert_eq!(generator(""), Err(Error::EmptyInput));
+        assert_eq!(
+            generator("00100\n1111\n"),
+            Err(Error::InconsistentWidth {
+                line: 2,
+                expected: 5,
+                found: 4
+            })
+        );
+        assert!(matches!(
+            generator("00100\n11210\n"),
+            Err(Error::Parse {
+                line: 2,
+                column: 3,
+                ..
+            })
+        ));
+    }
 
-        assert_eq!(problem, generator(input));
+    #[test]
+    fn wide_readings() {
+        // both answers are too large for a u32
+        let input = "11111111111111111111111111111111\n11111111111111110000000000000000\n00000000000000000000000000000010";
+        let problem = generator(input).unwrap();
+        let gamma = 0xffff_0002_u128;
+        assert_eq!(part1(&problem), Ok(gamma * (gamma ^ 0xffff_ffff)));
+        assert_eq!(part1_streaming(input.as_bytes()), part1(&problem));
+        assert_eq!(part2(&problem), Ok(0xffff_ffff * 2));
+    }
+
+    #[test]
+    fn readings_64_bits() {
+        let input = [u64::MAX, u64::MAX << 32, 1 << 40]
+            .map(|x| format!("{x:064b}"))
+            .join("\n");
+        let problem = generator(&input).unwrap();
+        assert_eq!(problem.data, [u64::MAX, u64::MAX << 32, 1 << 40]);
+
+        let gamma = u128::from(u64::MAX << 32 | 1 << 40);
+        let expected = Ok(gamma * (gamma ^ u128::from(u64::MAX)));
+        assert_eq!(part1(&problem), expected);
+        assert_eq!(part1_streaming(input.as_bytes()), expected);
+        assert_eq!(part2(&problem), Ok(u128::from(u64::MAX) * (1 << 40)));
+        assert_eq!(part2_sorted(&problem), part2(&problem));
+    }
+
+    #[test]
+    fn readings_200_bits() {
+        // the first bit is mostly zeros, so the oxygen rating keeps the two readings with a zero
+        // there, and then the one with a one at bit 100, where they are tied; the CO2 rating is
+        // the only reading with a one in its first bit
+        let mut readings = vec!["0".repeat(200); 3];
+        readings[0].replace_range(..1, "1");
+        readings[1].replace_range(199.., "1");
+        readings[2].replace_range(100..101, "1");
+        let input = readings.join("\n");
+        let problem = generator(&input).unwrap();
+        assert_eq!(problem.data.len(), 3 * 4);
+        assert_eq!(problem.data[..4], [1 << 63, 0, 0, 0]);
+        assert_eq!(problem.data[4..8], [0, 0, 0, 1 << 56]);
+
+        // gamma is all zeros, so epsilon is all ones and doesn't fit in a u128
+        assert_eq!(part1(&problem), Err(Error::AnswerTooLarge));
+        assert_eq!(
+            part1_streaming(input.as_bytes()),
+            Err(Error::AnswerTooLarge)
+        );
+        let report = DiagnosticReport::new(&problem);
+        assert_eq!(report.oxygen.reading().unwrap().to_string(), readings[2]);
+        assert_eq!(report.co2.reading().unwrap().to_string(), readings[0]);
+        // their product is 2^99 * 2^199
+        assert_eq!(part2(&problem), Err(Error::AnswerTooLarge));
+        assert_eq!(part2_sorted(&problem), Err(Error::AnswerTooLarge));
+
+        // with a single reading both ratings are that reading, which can be small enough to fit,
+        // although epsilon never does
+        let single = "0".repeat(197) + "101";
+        let problem = generator(&single).unwrap();
+        assert_eq!(part1(&problem), Err(Error::AnswerTooLarge));
+        assert_eq!(part2(&problem), Ok(5 * 5));
+    }
+
+    #[test]
+    fn readings_100_bits() {
+        // gamma is small enough that its product with epsilon, nearly 2^100, fits in a u128
+        let input = ["0".repeat(97) + "101", "0".repeat(97) + "111"].join("\n");
+        let problem = generator(&input).unwrap();
+        let gamma = 0b101_u128;
+        let expected = Ok(gamma * (((1 << 100) - 1) ^ gamma));
+        assert_eq!(part1(&problem), expected);
+        assert_eq!(part1_streaming(input.as_bytes()), expected);
+        assert_eq!(
+            DiagnosticReport::new(&problem).power_consumption(),
+            expected
+        );
+
+        // but not once gamma has a one in its top half
+        let input = ["1".repeat(50) + &"0".repeat(50), "1".repeat(100)].join("\n");
+        let problem = generator(&input).unwrap();
+        assert_eq!(part1(&problem), Err(Error::AnswerTooLarge));
+    }
+
+    #[test]
+    fn test_part2_no_solution() {
+        let input = Problem {
+            num_bits: 2,
+            data: vec![],
+        };
+        assert_eq!(
+            part2(&input),
+            Err(Error::UndeterminedRating {
+                rating: "oxygen generator".into(),
+                remaining: 0
+            })
+        );
     }
 }
@@ -1,9 +1,11 @@
 use aoc_runner_derive::aoc;
 use std::iter::*;
 
+use crate::error::{self, parse_field, Error};
+
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 struct Number {
-    pub number: u8,
+    pub number: u32,
     pub marked: bool,
 }
 
@@ -19,13 +21,15 @@ impl Number {
 
 #[derive(Debug)]
 struct Board {
-    board: [Number; 25],
+    columns: usize,
+    /// The numbers, row by row.
+    board: Vec<Number>,
 }
 
 impl Board {
     /// Marks `called_number` if it exists on the board.  Returns true if it was marked, false
     /// otherwise.
-    fn update(&mut self, called_number: u8) -> bool {
+    fn update(&mut self, called_number: u32) -> bool {
         if let Some(cell) = self
             .board
             .iter_mut()
@@ -38,84 +42,138 @@ impl Board {
         }
     }
 
+    /// Returns true if every number in a row or a column is marked.
     fn check(&self) -> bool {
-        const ROW_OFFSETS: [usize; 5] = [0, 5, 10, 15, 20];
-        const COLUMN_OFFSETS: [usize; 5] = [0, 1, 2, 3, 4];
-        for i in 0..5 {
-            if ROW_OFFSETS
-                .iter()
-                .map(|x| self.board[x + i])
-                .all(|x| x.is_marked())
-            {
-                return true;
-            }
-            if COLUMN_OFFSETS
+        let full_row = self
+            .board
+            .chunks(self.columns)
+            .any(|row| row.iter().all(Number::is_marked));
+        let full_column = (0..self.columns).any(|column| {
+            self.board[column..]
                 .iter()
-                .map(|x| self.board[5 * i + x])
-                .all(|x| x.is_marked())
-            {
-                return true;
-            }
-        }
-
-        false
+                .step_by(self.columns)
+                .all(Number::is_marked)
+        });
+        full_row || full_column
     }
 
-    fn score(&self, last_called: u8) -> u32 {
-        let remaining_sum: u32 = self
+    /// The sum of the unmarked numbers times the last number called, or an overflow error.
+    fn score(&self, last_called: u32) -> error::Result<u64> {
+        let remaining_sum = self
             .board
             .iter()
             .filter(|num| num.is_unmarked())
-            .map(|num| num.number as u32)
-            .sum();
-        remaining_sum * (last_called as u32)
+            .try_fold(0_u64, |sum, num| sum.checked_add(u64::from(num.number)))
+            .ok_or(Error::Overflow)?;
+        remaining_sum
+            .checked_mul(u64::from(last_called))
+            .ok_or(Error::Overflow)
     }
 }
 
 pub struct Bingo {
-    numbers: Vec<u8>,
+    numbers: Vec<u32>,
     boards: Vec<Board>,
 }
 
-// #[aoc_generator(day5)]
-fn generator(input: &str) -> Bingo {
-    let (first, rest) = input.split_once('\n').unwrap();
+/// `count` rows, in words.
+fn rows_text(count: usize) -> String {
+    match count {
+        1 => "1 row".to_string(),
+        _ => format!("{count} rows"),
+    }
+}
+
+/// Turns the rows collected for one board into a `Board`, checking that it has as many rows as
+/// the first board, or setting that number if this is the first.  `start` is the 1-based line the
+/// board begins on.
+fn finish_board(
+    cells: &mut Vec<Number>,
+    start: usize,
+    columns: usize,
+    rows: &mut Option<usize>,
+) -> error::Result<Board> {
+    let found = cells.len() / columns;
+    let expected = *rows.get_or_insert(found);
+    if found != expected {
+        return Err(Error::Parse {
+            line: start,
+            column: 1,
+            message: format!("board has {}, expected {expected}", rows_text(found)),
+        });
+    }
+    Ok(Board {
+        columns,
+        board: std::mem::take(cells),
+    })
+}
+
+/// Boards can have any number of rows and columns, but they must all have the same shape as the
+/// first.
+fn generator(input: &str) -> error::Result<Bingo> {
+    let mut lines = input.lines().enumerate();
+    let (_, first) = lines.next().ok_or(Error::EmptyInput)?;
+    if first.trim().is_empty() {
+        return Err(Error::EmptyInput);
+    }
     let numbers = first
         .split(',')
-        .map(|s| s.parse().unwrap())
-        .collect::<Vec<u8>>();
-
-    let boards = rest
-        .split('\n')
-        .collect::<Vec<_>>()
-        .as_slice()
-        .chunks(6)
-        .map(|a| {
-            let mut board = [Number::default(); 25];
-
-            a.iter()
-                .skip(1) //ignore leading whitespace
-                .flat_map(|s| {
-                    s.split_ascii_whitespace().map(|i| Number {
-                        number: i.parse().unwrap(),
-                        marked: false,
-                    })
-                })
-                .enumerate()
-                .for_each(|(i, num)| {
-                    board[i] = num;
-                });
+        .map(|s| parse_field(1, first, s))
+        .collect::<error::Result<Vec<u32>>>()?;
+
+    let mut boards = vec![];
+    let mut cells = vec![];
+    let mut start = 0;
+    let mut columns = None;
+    let mut rows = None;
+    for (i, line) in lines {
+        if line.trim().is_empty() {
+            if let Some(columns) = columns.filter(|_| !cells.is_empty()) {
+                boards.push(finish_board(&mut cells, start, columns, &mut rows)?);
+            }
+            continue;
+        }
+        if cells.is_empty() {
+            start = i + 1;
+        }
 
-            Board { board }
-        })
-        .collect::<Vec<_>>();
+        let row = line
+            .split_ascii_whitespace()
+            .map(|s| {
+                Ok(Number {
+                    number: parse_field(i + 1, line, s)?,
+                    marked: false,
+                })
+            })
+            .collect::<error::Result<Vec<_>>>()?;
+        let expected = *columns.get_or_insert(row.len());
+        if row.len() != expected {
+            return Err(Error::InconsistentWidth {
+                line: i + 1,
+                expected,
+                found: row.len(),
+            });
+        }
+        if let Some(rows) = rows.filter(|&rows| cells.len() == rows * expected) {
+            return Err(Error::at(
+                i + 1,
+                line,
+                line,
+                format!("board has more than {}", rows_text(rows)),
+            ));
+        }
+        cells.extend(row);
+    }
+    if let Some(columns) = columns.filter(|_| !cells.is_empty()) {
+        boards.push(finish_board(&mut cells, start, columns, &mut rows)?);
+    }
 
-    Bingo { numbers, boards }
+    Ok(Bingo { numbers, boards })
 }
 
 #[aoc(day4, part1)]
-fn part1(input: &str) -> u32 {
-    let mut bingo = generator(input);
+fn part1(input: &str) -> error::Result<u64> {
+    let mut bingo = generator(input)?;
     for number in bingo.numbers.into_iter() {
         if let Some(matching_board) = bingo.boards.iter_mut().find_map(|board| {
             board.update(number);
@@ -129,12 +187,12 @@ fn part1(input: &str) -> u32 {
         }
     }
 
-    panic!("Unable to find a winning board!")
+    Err(Error::NoSolution)
 }
 
 #[aoc(day4, part2)]
-fn part2(input: &str) -> u32 {
-    let mut bingo = generator(input);
+fn part2(input: &str) -> error::Result<u64> {
+    let mut bingo = generator(input)?;
     let num_boards = bingo.boards.len();
     let mut winning_boards = vec![false; num_boards];
     let mut num_false = num_boards;
@@ -155,5 +213,168 @@ fn part2(input: &str) -> u32 {
         }
     }
 
-    panic!("Board not found")
+    Err(Error::NoSolution)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &str = "\
+7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+
+22 13 17 11  0
+ 8  2 23  4 24
+21  9 14 16  7
+ 6 10  3 18  5
+ 1 12 20 15 19
+
+ 3 15  0  2 22
+ 9 18 13 17  5
+19  8  7 25 23
+20 11 10 24  4
+14 21 16 12  6
+
+14 21 17 24  4
+10 16 15  9 19
+18  8 23 26 20
+22 11 13  6  5
+ 2  0 12  3  7";
+
+    #[test]
+    fn sample_data() {
+        assert_eq!(part1(SAMPLE), Ok(4512));
+        assert_eq!(part2(SAMPLE), Ok(1924));
+    }
+
+    #[test]
+    fn small_boards() {
+        let input = "\
+1,2,3,4,5,6,7,8,9
+
+1 5 9
+2 6 7
+3 4 8
+
+9 8 7
+1 5 4
+3 6 2";
+        // the first board's first column is complete after 3, the second's middle row after 5
+        assert_eq!(part1(input), Ok((5 + 9 + 6 + 7 + 4 + 8) * 3));
+        assert_eq!(part2(input), Ok((9 + 8 + 7 + 6) * 5));
+
+        // boards don't have to be square
+        assert_eq!(part1("1,4\n\n1 2 3\n4 5 6"), Ok((2 + 3 + 5 + 6) * 4));
+    }
+
+    /// A `side` by `side` board of the numbers from `first` up, in order.
+    fn square_board(first: u64, side: u64) -> String {
+        (0..side)
+            .map(|row| {
+                (0..side)
+                    .map(|column| (first + side * row + column).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn large_boards() {
+        // 20x20 boards have more numbers than fit in a byte
+        for side in [10, 20] {
+            let cells = side * side;
+            // the second board's first row, then the first board's first column
+            let numbers = (cells..cells + side)
+                .chain((0..cells).step_by(side as usize))
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            let input = format!(
+                "{numbers}\n\n{}\n\n{}",
+                square_board(0, side),
+                square_board(cells, side)
+            );
+            let bingo = generator(&input).unwrap();
+            assert_eq!(bingo.boards[1].board.len(), cells as usize);
+
+            let sum = |numbers: std::ops::Range<u64>| numbers.sum::<u64>();
+            let first_row = sum(cells..cells + side);
+            assert_eq!(
+                part1(&input),
+                Ok((sum(cells..2 * cells) - first_row) * (cells + side - 1))
+            );
+            let first_column = (0..cells).step_by(side as usize).sum::<u64>();
+            assert_eq!(
+                part2(&input),
+                Ok((sum(0..cells) - first_column) * (cells - side))
+            );
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let input = format!(
+            "{0},{1}\n\n{0} {1}\n{2} {3}",
+            u32::MAX,
+            u32::MAX - 1,
+            u32::MAX - 2,
+            u32::MAX - 3
+        );
+        assert_eq!(part1(&input), Err(Error::Overflow));
+        assert_eq!(part1("300,256\n\n300 256\n7 8"), Ok((7 + 8) * 256));
+    }
+
+    #[test]
+    fn mismatched_boards() {
+        assert_eq!(
+            generator("1\n\n1 2 3\n4 5 6\n\n1 2\n3 4").err(),
+            Some(Error::InconsistentWidth {
+                line: 6,
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            generator("1\n\n1 2\n3 4\n\n1 2").err(),
+            Some(Error::Parse {
+                line: 6,
+                column: 1,
+                message: "board has 1 row, expected 2".into()
+            })
+        );
+        assert_eq!(
+            generator("1\n\n1 2\n3 4\n5 6\n\n1 2").err(),
+            Some(Error::Parse {
+                line: 7,
+                column: 1,
+                message: "board has 1 row, expected 3".into()
+            })
+        );
+        assert_eq!(
+            generator("1\n\n1 2\n3 4\n\n1 2\n3 4\n5 6").err(),
+            Some(Error::Parse {
+                line: 8,
+                column: 1,
+                message: "board has more than 2 rows".into()
+            })
+        );
+        assert_eq!(
+            generator("1\n\n1 2\n\n1 2\n3 4").err(),
+            Some(Error::Parse {
+                line: 6,
+                column: 1,
+                message: "board has more than 1 row".into()
+            })
+        );
+        assert!(matches!(
+            generator("1\n\n1 2\n3 x"),
+            Err(Error::Parse {
+                line: 4,
+                column: 3,
+                ..
+            })
+        ));
+    }
 }
@@ -1,5 +1,13 @@
+use std::io::BufRead;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::{
+    error::{self, parse_field, Error},
+    grid::Grid,
+    streaming::for_each_line,
+};
+
 #[derive(PartialEq, Eq)]
 enum Direction {
     Horizontal,
@@ -21,20 +29,25 @@ struct Line {
 }
 
 impl Line {
-    fn parse(data: &str) -> Line {
-        let (first, rest) = data.split_once(' ').expect("improperly formatted data");
-        let (_, last) = rest.rsplit_once(' ').expect("improperly formatted data");
-        let (a, b) = first.split_once(',').unwrap();
-        let p1 = Point {
-            x: a.parse::<u32>().unwrap(),
-            y: b.parse::<u32>().unwrap(),
-        };
-        let (a, b) = last.split_once(',').unwrap();
-        let p2 = Point {
-            x: a.parse::<u32>().unwrap(),
-            y: b.parse::<u32>().unwrap(),
+    /// Parses a line of the form `x1,y1 -> x2,y2`.  `line_no` is the 1-based line number of
+    /// `data`, used for error reporting.
+    fn parse(line_no: usize, data: &str) -> error::Result<Line> {
+        let malformed = || Error::at(line_no, data, data, "expected `x1,y1 -> x2,y2`");
+        let (first, rest) = data.split_once(' ').ok_or_else(malformed)?;
+        let (_, last) = rest.rsplit_once(' ').ok_or_else(malformed)?;
+        let point = |s: &str| -> error::Result<Point> {
+            let (a, b) = s
+                .split_once(',')
+                .ok_or_else(|| Error::at(line_no, data, s, "expected `x,y`"))?;
+            Ok(Point {
+                x: parse_field(line_no, data, a)?,
+                y: parse_field(line_no, data, b)?,
+            })
         };
-        Line { p1, p2 }
+        Ok(Line {
+            p1: point(first)?,
+            p2: point(last)?,
+        })
     }
 
     fn direction(&self) -> Direction {
@@ -56,25 +69,23 @@ impl Line {
             Direction::Vertical => {
                 let min = self.p1.y.min(self.p2.y);
                 let max = self.p1.y.max(self.p2.y);
-                Box::new((min..=max).into_iter().map(|y| Point { x: self.p1.x, y }))
+                Box::new((min..=max).map(|y| Point { x: self.p1.x, y }))
             }
             Direction::Horizontal => {
                 let min = self.p1.x.min(self.p2.x);
                 let max = self.p1.x.max(self.p2.x);
-                Box::new((min..=max).into_iter().map(|x| Point { x, y: self.p1.y }))
+                Box::new((min..=max).map(|x| Point { x, y: self.p1.y }))
             }
             Direction::Diagonal => {
-                // start at p1, walk to p2.
-                let x_dir = if self.p1.x < self.p2.x { 1 } else { -1_i32 };
-                let y_dir = if self.p1.y < self.p2.y { 1 } else { -1_i32 };
+                // start at p1, walk to p2.  i64 holds any coordinate and any step between two of
+                // them, and every point walked lies between p1 and p2, so it fits back in a u32.
+                let x_dir = if self.p1.x < self.p2.x { 1 } else { -1_i64 };
+                let y_dir = if self.p1.y < self.p2.y { 1 } else { -1_i64 };
                 Box::new(
-                    (0..=(self.p1.x.abs_diff(self.p2.x)))
-                        .into_iter()
-                        .map(|x| x as i32)
-                        .map(move |x| Point {
-                            x: ((self.p1.x as i32) + x * x_dir) as u32,
-                            y: ((self.p1.y as i32) + x * y_dir) as u32,
-                        }),
+                    (0..=i64::from(self.p1.x.abs_diff(self.p2.x))).map(move |step| Point {
+                        x: (i64::from(self.p1.x) + step * x_dir) as u32,
+                        y: (i64::from(self.p1.y) + step * y_dir) as u32,
+                    }),
                 )
             }
             _ => panic!(),
@@ -89,47 +100,113 @@ struct Map {
     dim_y: u32,
 }
 
-struct Grid {
-    data: Vec<u8>,
-    dim_y: u32,
+/// Counts how many lines cover each point of the map.
+struct Coverage {
+    grid: Grid<u8>,
 }
 
-impl Grid {
-    fn new(x: u32, y: u32) -> Grid {
-        Grid {
-            data: vec![0_u8; ((x + 1) as usize) * ((y + 1) as usize)],
-            dim_y: y,
+impl Coverage {
+    fn new(x: u32, y: u32) -> Coverage {
+        Coverage {
+            grid: Grid::new(x as usize + 1, y as usize + 1, 0),
         }
     }
 
     fn increment_point(&mut self, point: &Point) {
-        self.data[usize::try_from(point.x * self.dim_y + point.y).unwrap()] += 1;
+        let cell = &mut self.grid[(point.x as usize, point.y as usize)];
+        *cell = cell.saturating_add(1);
     }
 
     #[cfg(test)]
     fn get(&self, point: &Point) -> Option<u8> {
-        self.data
-            .get(usize::try_from(point.x * self.dim_y + point.y).unwrap())
-            .cloned()
+        self.grid.get(point.x as usize, point.y as usize).copied()
+    }
+
+    /// Grows the map, if necessary, so that it covers `point`.  Each dimension at least doubles
+    /// when it grows, so a stream of lines only causes a handful of copies.
+    fn grow_to_fit(&mut self, point: &Point) {
+        let (x, y) = (point.x as usize, point.y as usize);
+        let (width, height) = (self.grid.width(), self.grid.height());
+        if x < width && y < height {
+            return;
+        }
+
+        let new_width = if x < width {
+            width
+        } else {
+            (x + 1).max(width * 2)
+        };
+        let new_height = if y < height {
+            height
+        } else {
+            (y + 1).max(height * 2)
+        };
+        let mut grid = Grid::new(new_width, new_height, 0);
+        for (cx, cy) in self.grid.positions() {
+            grid[(cx, cy)] = self.grid[(cx, cy)];
+        }
+        self.grid = grid;
+    }
+
+    /// Returns the number of points covered by at least two lines.
+    fn overlaps(&self) -> usize {
+        self.grid.iter().filter(|x| **x >= 2).count()
     }
 }
 
 #[aoc_generator(day5)]
-fn generator(input: &str) -> Map {
-    let lines = input.lines().map(Line::parse).collect::<Vec<_>>();
-    let max_x = lines.iter().map(|l| l.p1.x.max(l.p2.x)).max().unwrap();
-    let max_y = lines.iter().map(|l| l.p1.y.max(l.p2.y)).max().unwrap();
+fn generator(input: &str) -> error::Result<Map> {
+    let lines = input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| Line::parse(i + 1, l))
+        .collect::<error::Result<Vec<_>>>()?;
+    let max_x = lines
+        .iter()
+        .map(|l| l.p1.x.max(l.p2.x))
+        .max()
+        .ok_or(Error::EmptyInput)?;
+    let max_y = lines
+        .iter()
+        .map(|l| l.p1.y.max(l.p2.y))
+        .max()
+        .ok_or(Error::EmptyInput)?;
 
-    Map {
+    Ok(Map {
         lines,
         dim_x: max_x,
         dim_y: max_y,
+    })
+}
+
+/// Streaming version of `part1` (or `part2`, if `diagonals` is set).  Only the map is kept in
+/// memory, so memory use depends on the extent of the lines rather than how many there are.
+pub(crate) fn overlaps_streaming(input: impl BufRead, diagonals: bool) -> error::Result<usize> {
+    let mut grid = Coverage::new(0, 0);
+    let lines = for_each_line(input, |line_no, data| {
+        let line = Line::parse(line_no, data)?;
+        match line.direction() {
+            Direction::Horizontal | Direction::Vertical => {}
+            Direction::Diagonal if diagonals => {}
+            _ => return Ok(()),
+        }
+        grid.grow_to_fit(&line.p1);
+        grid.grow_to_fit(&line.p2);
+        for p in line.line_iter() {
+            grid.increment_point(&p);
+        }
+        Ok(())
+    })?;
+    if lines == 0 {
+        return Err(Error::EmptyInput);
     }
+
+    Ok(grid.overlaps())
 }
 
 #[aoc(day5, part1)]
-fn part1(input: &Map) -> usize {
-    let mut grid = Grid::new(input.dim_x, input.dim_y);
+fn part1(input: &Map) -> error::Result<usize> {
+    let mut grid = Coverage::new(input.dim_x, input.dim_y);
     for line in &input.lines {
         let direction = line.direction();
         if direction != Direction::Horizontal && direction != Direction::Vertical {
@@ -140,12 +217,12 @@ fn part1(input: &Map) -> usize {
         }
     }
 
-    grid.data.iter().filter(|x| **x >= 2).count()
+    Ok(grid.overlaps())
 }
 
 #[aoc(day5, part2)]
-fn part2(input: &Map) -> usize {
-    let mut grid = Grid::new(input.dim_x, input.dim_y);
+fn part2(input: &Map) -> error::Result<usize> {
+    let mut grid = Coverage::new(input.dim_x, input.dim_y);
     for line in &input.lines {
         if line.direction() == Direction::Other {
             continue;
@@ -155,17 +232,75 @@ fn part2(input: &Map) -> usize {
         }
     }
 
-    grid.data.iter().filter(|x| **x >= 2).count()
+    Ok(grid.overlaps())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    /// Counts overlapping points by walking each line one step at a time into a hash map.
+    fn naive_overlaps(lines: &[(i64, i64, i64, i64)], diagonals: bool) -> usize {
+        let mut counts = HashMap::<(i64, i64), usize>::new();
+        for &(x1, y1, x2, y2) in lines {
+            let (dx, dy) = (x2 - x1, y2 - y1);
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal && (!diagonals || dx.abs() != dy.abs()) {
+                continue;
+            }
+            for i in 0..=dx.abs().max(dy.abs()) {
+                *counts
+                    .entry((x1 + i * dx.signum(), y1 + i * dy.signum()))
+                    .or_default() += 1;
+            }
+        }
+        counts.values().filter(|c| **c >= 2).count()
+    }
+
+    /// Horizontal, vertical, 45 degree and arbitrary segments within a small area, so that
+    /// overlaps are common.
+    fn segments() -> impl Strategy<Value = Vec<(i64, i64, i64, i64)>> {
+        let segment =
+            (0..30_i64, 0..30_i64, 0..30_i64, 0..4).prop_map(|(x, y, n, kind)| match kind {
+                0 => (x, y, n, y),
+                1 => (x, y, x, n),
+                2 => {
+                    let len = n.min(29 - x).min(29 - y);
+                    (x, y, x + len, y + len)
+                }
+                _ => (x, y, n, (y + n) % 30),
+            });
+        prop::collection::vec(segment, 1..40)
+    }
+
+    fn to_input(lines: &[(i64, i64, i64, i64)]) -> String {
+        lines
+            .iter()
+            .map(|(x1, y1, x2, y2)| format!("{x1},{y1} -> {x2},{y2}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    proptest! {
+        #[test]
+        fn part1_matches_naive(lines in segments()) {
+            let map = generator(&to_input(&lines)).unwrap();
+            prop_assert_eq!(part1(&map), Ok(naive_overlaps(&lines, false)));
+        }
+
+        #[test]
+        fn part2_matches_naive(lines in segments()) {
+            let map = generator(&to_input(&lines)).unwrap();
+            prop_assert_eq!(part2(&map), Ok(naive_overlaps(&lines, true)));
+        }
+    }
 
     #[test]
     fn test_increment_point() {
         let point = Point { x: 1, y: 2 };
-        let mut grid = Grid::new(1, 2);
+        let mut grid = Coverage::new(1, 2);
 
         grid.increment_point(&point);
         assert_eq!(grid.get(&point), Some(1));
@@ -177,7 +312,7 @@ mod test {
     #[test]
     fn test_parse_line() {
         let input = "445,187 -> 912,654";
-        let line = Line::parse(&input);
+        let line = Line::parse(1, input).unwrap();
         assert_eq!(line.p1, Point { x: 445, y: 187 });
         assert_eq!(line.p2, Point { x: 912, y: 654 });
     }
@@ -185,20 +320,20 @@ mod test {
     #[test]
     fn test_example_part1() {
         let input = include_str!("../input/2021/day5_test.txt");
-        let map = generator(input);
-        assert_eq!(part1(&map), 5)
+        let map = generator(input).unwrap();
+        assert_eq!(part1(&map), Ok(5))
     }
 
     #[test]
     fn test_example_part2() {
         let input = include_str!("../input/2021/day5_test.txt");
-        let map = generator(input);
-        assert_eq!(part2(&map), 12)
+        let map = generator(input).unwrap();
+        assert_eq!(part2(&map), Ok(12))
     }
 
     #[test]
     fn test_line_iter_horizontal() {
-        let line = Line::parse("4,5 -> 8,5");
+        let line = Line::parse(1, "4,5 -> 8,5").unwrap();
         let mut iter = line.line_iter();
         assert_eq!(iter.next(), Some(Point { x: 4, y: 5 }));
         assert_eq!(iter.next(), Some(Point { x: 5, y: 5 }));
@@ -210,7 +345,7 @@ mod test {
 
     #[test]
     fn test_line_iter_vertical() {
-        let line = Line::parse("4,5 -> 4,8");
+        let line = Line::parse(1, "4,5 -> 4,8").unwrap();
         let mut iter = line.line_iter();
         assert_eq!(iter.next(), Some(Point { x: 4, y: 5 }));
         assert_eq!(iter.next(), Some(Point { x: 4, y: 6 }));
@@ -221,12 +356,48 @@ mod test {
 
     #[test]
     fn test_line_iter_diagonal() {
-        let line = Line::parse("3,5 -> 6,8");
+        let line = Line::parse(1, "3,5 -> 6,8").unwrap();
         let mut iter = line.line_iter();
         assert_eq!(iter.next(), Some(Point { x: 3, y: 5 }));
         assert_eq!(iter.next(), Some(Point { x: 4, y: 6 }));
         assert_eq!(iter.next(), Some(Point { x: 5, y: 7 }));
         assert_eq!(iter.next(), Some(Point { x: 6, y: 8 }));
         assert_eq!(iter.next(), None);
+
+        // coordinates past i32::MAX, walking both up and down
+        let line = Line::parse(1, "2147483647,4294967295 -> 2147483649,4294967293").unwrap();
+        assert_eq!(
+            line.line_iter().map(|p| (p.x, p.y)).collect::<Vec<_>>(),
+            [
+                (2147483647, 4294967295),
+                (2147483648, 4294967294),
+                (2147483649, 4294967293)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert!(matches!(
+            Line::parse(4, "445,187 -> 912"),
+            Err(Error::Parse {
+                line: 4,
+                column: 12,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Line::parse(2, "445,1x7 -> 912,654"),
+            Err(Error::Parse {
+                line: 2,
+                column: 5,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Line::parse(1, "445,187"),
+            Err(Error::Parse { line: 1, .. })
+        ));
+        assert_eq!(generator("").err(), Some(Error::EmptyInput));
     }
 }
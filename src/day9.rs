@@ -1,88 +1,64 @@
 use rayon::prelude::*;
-use std::{
-    borrow::Cow,
-    collections::{BTreeSet, VecDeque},
-    str::FromStr,
-};
+use std::collections::{BTreeSet, VecDeque};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
-struct Grid {
-    width: isize,
-    height: isize,
-    data: Vec<u8>,
+use crate::{
+    error::{self, Error},
+    grid::Grid,
+};
+
+struct Heightmap {
+    grid: Grid<u8>,
 }
 
-impl Grid {
+impl Heightmap {
     pub fn find_low_points(&self) -> Vec<u8> {
-        let mut points = vec![];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                if let Some(i) = self.is_low_point(x, y) {
-                    points.push(i)
-                }
-            }
-        }
-        points
+        self.grid
+            .positions()
+            .filter_map(|(x, y)| self.is_low_point(x, y))
+            .collect()
     }
 
-    pub fn is_low_point(&self, x: isize, y: isize) -> Option<u8> {
-        let value = self.try_get(x, y)?;
+    pub fn is_low_point(&self, x: usize, y: usize) -> Option<u8> {
+        let value = *self.grid.get(x, y)?;
         if value == 9 {
             return None;
         };
-        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-            if let Some(p) = self.try_get(x + dx, y + dy) {
-                if value >= p {
-                    return None;
-                };
-            }
+        for (nx, ny) in self.grid.neighbours4(x, y) {
+            if value >= self.grid[(nx, ny)] {
+                return None;
+            };
         }
         Some(value)
     }
 
-    fn try_get(&self, x: isize, y: isize) -> Option<u8> {
-        if x < 0 || x >= self.width || y < 0 || y >= self.height {
-            None
-        } else {
-            self.data
-                .get((y * self.width + x) as usize)
-                .copied()
-        }
-    }
-
-    pub fn basin_at(&self, x_pos: isize, y_pos: isize) -> BTreeSet<(isize, isize)> {
+    pub fn basin_at(&self, x_pos: usize, y_pos: usize) -> BTreeSet<(usize, usize)> {
         // starting at (x, y), flood fill until we hit 9s.
-        // keep track of points we've visit and want to visit; we don't want to check points twice.
+        // keep track of points we've visited or queued; we don't want to check points twice, and
+        // on large basins re-queueing points makes the queue grow without bound.
         let mut point_queue = VecDeque::new();
         let mut visited = BTreeSet::new();
         point_queue.push_back((x_pos, y_pos));
+        visited.insert((x_pos, y_pos));
 
         while let Some((x, y)) = point_queue.pop_front() {
-            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-                let new_x = x + dx;
-                let new_y = y + dy;
-                if visited.contains(&(new_x, new_y)) {
-                    continue;
-                }
-
-                if let Some(v) = self.try_get(new_x, new_y) {
-                    if v != 9 {
-                        // barrier
-                        point_queue.push_back((new_x, new_y))
-                    }
+            for (new_x, new_y) in self.grid.neighbours4(x, y) {
+                // 9s are the barrier between basins
+                if self.grid[(new_x, new_y)] != 9 && visited.insert((new_x, new_y)) {
+                    point_queue.push_back((new_x, new_y))
                 }
             }
-            visited.insert((x, y));
         }
 
         visited
     }
 
     pub fn basin_sizes(&self) -> Vec<u32> {
-        let mut basin_sizes = (0..self.height)
+        let width = self.grid.width();
+        let mut basin_sizes = (0..self.grid.height())
             .par_bridge()
-            .flat_map(|y| (0..self.width).map(move |x| (x, y)).collect::<Vec<_>>())
+            .flat_map(|y| (0..width).map(move |x| (x, y)).collect::<Vec<_>>())
             .filter(|(x, y)| self.is_low_point(*x, *y).is_some())
             .map(|(x, y)| self.basin_at(x, y).len() as u32)
             .collect::<Vec<_>>();
@@ -92,91 +68,118 @@ impl Grid {
 }
 
 #[aoc_generator(day9)]
-fn generator(input: &str) -> Grid {
-    // super annoying bug: aoc_runner strips trailing newlines from inputs, which makes calculating
-    // the height here tricky.
-    let input = if input.ends_with('\n') {
-        let mut x = String::from_str(input).unwrap();
-        x.push('\n');
-        Cow::Owned(x)
-    } else {
-        Cow::Borrowed(input)
-    };
-
-    let height = input.lines().count().try_into().unwrap();
-    let width = input
-        .split_once('\n')
-        .expect("newlines")
-        .0
-        .len()
-        .try_into()
-        .unwrap();
-    let data = input
-        .chars()
-        .filter(|c| *c != '\n')
-        .map(|c| {
-            c.to_digit(10)
-                .map(|x| x as u8)
-                .unwrap_or_else(|| panic!("failed to parse grid entry {c}"))
-        })
-        .collect::<Vec<_>>();
-
-    Grid {
-        width,
-        height,
-        data,
-    }
+fn generator(input: &str) -> error::Result<Heightmap> {
+    Ok(Heightmap {
+        grid: Grid::parse_digits(input)?,
+    })
 }
 
 #[aoc(day9, part1)]
-fn part1(input: &Grid) -> anyhow::Result<u32> {
+fn part1(input: &Heightmap) -> error::Result<usize> {
     let low_points = input.find_low_points();
-    Ok(low_points
-        .iter()
-        .map(|x| Into::<u32>::into(*x))
-        .sum::<u32>()
-        + TryInto::<u32>::try_into(low_points.len())?)
+    Ok(low_points.iter().map(|x| usize::from(*x) + 1).sum())
 }
 
 #[aoc(day9, part2)]
-fn part2(input: &Grid) -> u32 {
-    let basin_sizes = input.basin_sizes();
-    let b1 = basin_sizes[0];
-    let b2 = basin_sizes[1];
-    let b3 = basin_sizes[2];
-
-    b1 * b2 * b3
+fn part2(input: &Heightmap) -> error::Result<u32> {
+    match input.basin_sizes()[..] {
+        [b1, b2, b3, ..] => b1
+            .checked_mul(b2)
+            .and_then(|product| product.checked_mul(b3))
+            .ok_or(Error::Overflow),
+        _ => Err(Error::NoSolution),
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+
+    /// Marks every non-9 cell reachable from `(x, y)` and returns how many were newly marked.
+    fn naive_flood(rows: &[Vec<u8>], seen: &mut Vec<Vec<bool>>, x: isize, y: isize) -> u32 {
+        if y < 0 || x < 0 || y as usize >= rows.len() || x as usize >= rows[0].len() {
+            return 0;
+        }
+        let (ux, uy) = (x as usize, y as usize);
+        if seen[uy][ux] || rows[uy][ux] == 9 {
+            return 0;
+        }
+        seen[uy][ux] = true;
+        1 + naive_flood(rows, seen, x - 1, y)
+            + naive_flood(rows, seen, x + 1, y)
+            + naive_flood(rows, seen, x, y - 1)
+            + naive_flood(rows, seen, x, y + 1)
+    }
+
+    /// Finds the low points by comparing each cell with its neighbours directly, then sizes each
+    /// one's basin with a recursive flood fill.
+    fn naive_basin_sizes(rows: &[Vec<u8>]) -> Vec<u32> {
+        let at = |x: isize, y: isize| {
+            rows.get(y as usize)
+                .and_then(|row| row.get(x as usize))
+                .copied()
+        };
+        let mut sizes = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &value) in row.iter().enumerate() {
+                let (x, y) = (x as isize, y as isize);
+                let neighbours = [at(x - 1, y), at(x + 1, y), at(x, y - 1), at(x, y + 1)];
+                if value != 9 && neighbours.iter().flatten().all(|n| value < *n) {
+                    let mut seen = vec![vec![false; row.len()]; rows.len()];
+                    sizes.push(naive_flood(rows, &mut seen, x, y));
+                }
+            }
+        }
+        sizes.sort_by(|a, b| b.cmp(a));
+        sizes
+    }
+
+    fn heightmap() -> impl Strategy<Value = Vec<Vec<u8>>> {
+        (1..20_usize, 1..20_usize).prop_flat_map(|(width, height)| {
+            prop::collection::vec(prop::collection::vec(0..=9_u8, width), height)
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn basin_sizes_match_naive(rows in heightmap()) {
+            let input = rows
+                .iter()
+                .map(|row| row.iter().map(|d| char::from(b'0' + d)).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let data = generator(&input).unwrap();
+            prop_assert_eq!(data.basin_sizes(), naive_basin_sizes(&rows));
+        }
+    }
+
     static EXAMPLE: &str = "2199943210\n3987894921\n9856789892\n8767896789\n9899965678\n";
     #[test]
     fn example_data() {
-        let data = generator(EXAMPLE);
+        let data = generator(EXAMPLE).unwrap();
         let result = part1(&data).unwrap();
         assert_eq!(result, 15);
     }
 
     #[test]
     fn example_data_result() {
-        let data = generator(EXAMPLE);
+        let data = generator(EXAMPLE).unwrap();
         let low_points = data.find_low_points();
         assert_eq!(&low_points, &[1, 0, 5, 5]);
     }
 
     #[test]
     fn parse_dimensions() {
-        let data = generator(EXAMPLE);
-        assert_eq!(data.width, 10);
-        assert_eq!(data.height, 5);
+        let data = generator(EXAMPLE).unwrap();
+        assert_eq!(data.grid.width(), 10);
+        assert_eq!(data.grid.height(), 5);
     }
 
     #[test]
     fn from_data_1() {
         let string_data = "43212\n54101\n65212\n";
-        let data = generator(string_data);
+        let data = generator(string_data).unwrap();
         let low_points = data.find_low_points();
         assert_eq!(&low_points, &[0]);
     }
@@ -184,29 +187,66 @@ mod test {
     #[test]
     fn from_data_2() {
         let string_data = "99199\n92019\n";
-        let data = generator(string_data);
+        let data = generator(string_data).unwrap();
         let low_points = data.find_low_points();
         assert_eq!(&low_points, &[0]);
     }
 
     #[test]
     fn parse_real_input_dimensions() {
-        let data = generator(include_str!("../input/2021/day9.txt"));
-        assert_eq!(data.width, 100);
-        assert_eq!(data.height, 100);
+        let data = generator(include_str!("../input/2021/day9.txt")).unwrap();
+        assert_eq!(data.grid.width(), 100);
+        assert_eq!(data.grid.height(), 100);
     }
 
     #[test]
     fn example_basins() {
-        let data = generator(EXAMPLE);
+        let data = generator(EXAMPLE).unwrap();
         let result = part2(&data);
-        assert_eq!(result, 1134);
+        assert_eq!(result, Ok(1134));
     }
 
     #[test]
     fn example_basin_sizes() {
-        let data = generator(EXAMPLE);
+        let data = generator(EXAMPLE).unwrap();
         let result = data.basin_sizes();
         assert_eq!(&result, &[14, 9, 9, 3]);
     }
+
+    #[test]
+    fn generator_errors() {
+        assert!(matches!(generator(""), Err(Error::EmptyInput)));
+        assert!(matches!(
+            generator("2199\n39x7\n"),
+            Err(Error::Parse {
+                line: 2,
+                column: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            generator("2199\n398\n"),
+            Err(Error::InconsistentWidth {
+                line: 2,
+                expected: 4,
+                found: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn too_few_basins() {
+        let data = generator("1991\n9999\n").unwrap();
+        assert_eq!(part2(&data), Err(Error::NoSolution));
+    }
+
+    #[test]
+    fn overflow() {
+        // three basins of 1700 cells, one per row, each draining to its first cell
+        let basin = format!("0{}", "1".repeat(1699));
+        let wall = "9".repeat(1700);
+        let data = generator(&[&*basin, &wall, &basin, &wall, &basin].join("\n")).unwrap();
+        assert_eq!(data.basin_sizes(), [1700; 3]);
+        assert_eq!(part2(&data), Err(Error::Overflow));
+    }
 }
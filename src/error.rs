@@ -0,0 +1,182 @@
+use std::{fmt, io, str::FromStr};
+
+/// Errors produced while parsing puzzle input or solving a puzzle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input could not be parsed.  `line` and `column` are 1-based.
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The input contained no data at all.
+    EmptyInput,
+    /// A row of the input has a different width to the rows before it.  `line` is 1-based.
+    InconsistentWidth {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The input was well-formed, but the puzzle has no answer for it.
+    NoSolution,
+    /// An intermediate value or the answer doesn't fit in the type used to compute it.
+    Overflow,
+    /// The puzzle has an answer, but it needs more than the 128 bits answers are returned in.
+    AnswerTooLarge,
+    /// Filtering readings for a rating left `remaining` readings rather than copies of a single
+    /// one: none at all, or several different ones.
+    UndeterminedRating { rating: String, remaining: usize },
+    /// Filtering readings for a rating reached a bit with as many ones as zeros, and the rating's
+    /// criteria don't break ties.
+    TiedBit { rating: String, column: usize },
+    /// The input could not be read.
+    Io(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Builds a parse error pointing at `field`, which must be a subslice of `line`.  `line_no` is
+    /// 1-based.
+    pub fn at(line_no: usize, line: &str, field: &str, message: impl Into<String>) -> Error {
+        Error::Parse {
+            line: line_no,
+            column: column_of(line, field),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "parse error at line {line}, column {column}: {message}"),
+            Error::EmptyInput => write!(f, "input is empty"),
+            Error::InconsistentWidth {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line} has width {found}, but previous lines have width {expected}"
+            ),
+            Error::NoSolution => write!(f, "no solution exists for this input"),
+            Error::Overflow => write!(f, "arithmetic overflow while solving this input"),
+            Error::AnswerTooLarge => write!(f, "the answer is too large to fit in 128 bits"),
+            Error::UndeterminedRating {
+                rating,
+                remaining: 0,
+            } => {
+                write!(f, "no reading is left for the {rating} rating")
+            }
+            Error::UndeterminedRating { rating, remaining } => write!(
+                f,
+                "the {rating} rating is ambiguous: {remaining} different readings are left"
+            ),
+            Error::TiedBit { rating, column } => write!(
+                f,
+                "the {rating} rating is undetermined: bit {column} has as many ones as zeros"
+            ),
+            Error::Io(message) => write!(f, "failed to read input: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e.to_string())
+    }
+}
+
+/// Returns the 1-based column at which `field` starts within `line`.  Falls back to column 1 if
+/// `field` isn't a subslice of `line`.
+fn column_of(line: &str, field: &str) -> usize {
+    let offset = (field.as_ptr() as usize).wrapping_sub(line.as_ptr() as usize);
+    if offset <= line.len() {
+        offset + 1
+    } else {
+        1
+    }
+}
+
+/// Parses `field`, a subslice of `line`, reporting failures at its position.  `line_no` is
+/// 1-based.
+pub fn parse_field<T>(line_no: usize, line: &str, field: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    field.parse().map_err(|e| {
+        Error::at(
+            line_no,
+            line,
+            field,
+            format!("invalid value {field:?}: {e}"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_field_reports_column() {
+        let line = "12 -> ab";
+        let err = parse_field::<u32>(3, line, &line[6..]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Parse {
+                line: 3,
+                column: 7,
+                ..
+            }
+        ));
+        assert_eq!(parse_field::<u32>(3, line, &line[..2]), Ok(12));
+    }
+
+    #[test]
+    fn display() {
+        let line = "1,x";
+        let err = Error::at(2, line, &line[2..], "expected a digit");
+        assert_eq!(
+            err.to_string(),
+            "parse error at line 2, column 3: expected a digit"
+        );
+        assert_eq!(
+            Error::InconsistentWidth {
+                line: 4,
+                expected: 5,
+                found: 3
+            }
+            .to_string(),
+            "line 4 has width 3, but previous lines have width 5"
+        );
+        assert_eq!(
+            Error::UndeterminedRating {
+                rating: "CO2 scrubber".into(),
+                remaining: 0
+            }
+            .to_string(),
+            "no reading is left for the CO2 scrubber rating"
+        );
+        assert_eq!(
+            Error::AnswerTooLarge.to_string(),
+            "the answer is too large to fit in 128 bits"
+        );
+        assert_eq!(
+            Error::TiedBit {
+                rating: "oxygen generator".into(),
+                column: 2
+            }
+            .to_string(),
+            "the oxygen generator rating is undetermined: bit 2 has as many ones as zeros"
+        );
+    }
+}
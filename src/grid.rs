@@ -0,0 +1,312 @@
+use std::{
+    fmt,
+    ops::{Index, IndexMut},
+};
+
+use crate::error::{self, Error};
+
+/// Offsets of the four orthogonal neighbours of a cell.
+const NEIGHBOURS_4: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// Offsets of all eight neighbours of a cell, including diagonals.
+const NEIGHBOURS_8: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// A dense 2D grid stored in row-major order.  Cells are addressed by `(x, y)`, where `x` is the
+/// column and `y` is the row, both starting at 0 in the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    data: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    /// Creates a `width` by `height` grid with every cell set to `value`.
+    pub fn new(width: usize, height: usize, value: T) -> Grid<T> {
+        Grid {
+            width,
+            height,
+            data: vec![value; width * height],
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// Creates a grid from row-major `data`.  Returns `None` if `data` doesn't hold exactly
+    /// `width * height` cells.
+    pub fn from_vec(width: usize, height: usize, data: Vec<T>) -> Option<Grid<T>> {
+        (data.len() == width * height).then_some(Grid {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Parses one cell per character, with one row per line.  `parse` returns `None` for
+    /// characters that aren't valid cells; `expected` describes what was expected instead.
+    pub fn parse_with(
+        input: &str,
+        expected: &str,
+        mut parse: impl FnMut(char) -> Option<T>,
+    ) -> error::Result<Grid<T>> {
+        let width = input
+            .lines()
+            .next()
+            .ok_or(Error::EmptyInput)?
+            .chars()
+            .count();
+        if width == 0 {
+            return Err(Error::EmptyInput);
+        }
+
+        let mut data = Vec::with_capacity(input.len());
+        let mut height = 0;
+        for (i, line) in input.lines().enumerate() {
+            let found = line.chars().count();
+            if found != width {
+                return Err(Error::InconsistentWidth {
+                    line: i + 1,
+                    expected: width,
+                    found,
+                });
+            }
+            for (j, c) in line.char_indices() {
+                let cell = parse(c).ok_or_else(|| {
+                    Error::at(
+                        i + 1,
+                        line,
+                        &line[j..],
+                        format!("expected {expected}, found {c:?}"),
+                    )
+                })?;
+                data.push(cell);
+            }
+            height += 1;
+        }
+
+        Ok(Grid {
+            width,
+            height,
+            data,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index_of(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then_some(y * self.width + x)
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it lies outside the grid.
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        self.index_of(x, y).map(|i| &self.data[i])
+    }
+
+    /// Returns the cell at `(x, y)` mutably, or `None` if it lies outside the grid.
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        self.index_of(x, y).map(|i| &mut self.data[i])
+    }
+
+    /// Returns the cell at `(x, y)` without any bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `x` must be less than `width()` and `y` less than `height()`.
+    pub unsafe fn get_unchecked(&self, x: usize, y: usize) -> &T {
+        self.data.get_unchecked(y * self.width + x)
+    }
+
+    /// Offsets `(x, y)` by `(dx, dy)`, returning `None` if the result lies outside the grid.
+    fn offset(&self, x: usize, y: usize, (dx, dy): (isize, isize)) -> Option<(usize, usize)> {
+        let x = x.checked_add_signed(dx)?;
+        let y = y.checked_add_signed(dy)?;
+        (x < self.width && y < self.height).then_some((x, y))
+    }
+
+    /// Returns the positions of the (up to four) cells orthogonally adjacent to `(x, y)`.
+    pub fn neighbours4(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        NEIGHBOURS_4
+            .into_iter()
+            .filter_map(move |d| self.offset(x, y, d))
+    }
+
+    /// Returns the positions of the (up to eight) cells adjacent to `(x, y)`, including
+    /// diagonals.
+    pub fn neighbours8(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        NEIGHBOURS_8
+            .into_iter()
+            .filter_map(move |d| self.offset(x, y, d))
+    }
+
+    /// Returns every position in the grid, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    /// Returns every cell in the grid, row by row.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    /// Returns row `y`.  Panics if `y` is out of bounds.
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.data[y * self.width..(y + 1) * self.width]
+    }
+
+    /// Returns each row of the grid, from top to bottom.
+    pub fn rows(&self) -> std::slice::Chunks<'_, T> {
+        self.data.chunks(self.width.max(1))
+    }
+
+    /// Returns the cells of column `x`, from top to bottom.  Panics if `x` is out of bounds.
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        assert!(x < self.width, "column {x} out of bounds");
+        self.data.iter().skip(x).step_by(self.width)
+    }
+
+    /// Returns each column of the grid, from left to right.
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(|x| self.column(x))
+    }
+}
+
+impl Grid<u8> {
+    /// Parses a map of single decimal digits, such as the day 9 heightmap.
+    pub fn parse_digits(input: &str) -> error::Result<Grid<u8>> {
+        Grid::parse_with(input, "a digit", |c| c.to_digit(10).map(|d| d as u8))
+    }
+}
+
+impl Grid<char> {
+    /// Parses a map of arbitrary characters.
+    pub fn parse_chars(input: &str) -> error::Result<Grid<char>> {
+        Grid::parse_with(input, "a character", Some)
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        match self.index_of(x, y) {
+            Some(i) => &self.data[i],
+            None => panic!(
+                "({x}, {y}) is outside a {}x{} grid",
+                self.width, self.height
+            ),
+        }
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        match self.index_of(x, y) {
+            Some(i) => &mut self.data[i],
+            None => panic!(
+                "({x}, {y}) is outside a {}x{} grid",
+                self.width, self.height
+            ),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.rows().enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{cell}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let input = "219\n398\n985";
+        let grid = Grid::parse_digits(input).unwrap();
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 3);
+        assert_eq!(grid[(2, 0)], 9);
+        assert_eq!(grid.get(0, 1), Some(&3));
+        assert_eq!(grid.get(3, 0), None);
+        assert_eq!(grid.to_string(), input);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Grid::parse_digits(""), Err(Error::EmptyInput));
+        assert_eq!(
+            Grid::parse_chars("ab\nc\n"),
+            Err(Error::InconsistentWidth {
+                line: 2,
+                expected: 2,
+                found: 1
+            })
+        );
+        assert!(matches!(
+            Grid::parse_digits("12\n3x\n"),
+            Err(Error::Parse {
+                line: 2,
+                column: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn neighbours() {
+        let grid = Grid::new(3, 2, 0);
+        assert_eq!(
+            grid.neighbours4(0, 0).collect::<Vec<_>>(),
+            &[(1, 0), (0, 1)]
+        );
+        assert_eq!(grid.neighbours4(1, 1).count(), 3);
+        assert_eq!(
+            grid.neighbours8(1, 0).collect::<Vec<_>>(),
+            &[(0, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let grid = Grid::from_vec(3, 2, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(grid.row(1), &[4, 5, 6]);
+        assert_eq!(grid.rows().collect::<Vec<_>>(), &[&[1, 2, 3], &[4, 5, 6]]);
+        assert_eq!(grid.column(1).copied().collect::<Vec<_>>(), &[2, 5]);
+        assert_eq!(
+            grid.columns()
+                .map(|c| c.copied().sum::<i32>())
+                .collect::<Vec<_>>(),
+            &[5, 7, 9]
+        );
+        assert_eq!(
+            grid.positions().collect::<Vec<_>>(),
+            &[(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+        );
+        assert!(Grid::from_vec(2, 2, vec![1, 2, 3]).is_none());
+    }
+}
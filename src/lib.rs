@@ -1,11 +1,18 @@
-#![feature(array_windows)]
+#![cfg_attr(feature = "nightly", feature(array_windows, portable_simd))]
+// `array_windows` has since been stabilised, so newer nightlies consider the attribute redundant.
+#![cfg_attr(feature = "nightly", allow(stable_features))]
 use aoc_runner_derive::aoc_lib;
 
-mod day1;
-mod day2;
-mod day3;
+pub mod day1;
+pub mod day2;
+pub mod day3;
 mod day4;
 mod day5;
 mod day9;
+pub mod error;
+pub mod grid;
+pub mod runner;
+pub mod streaming;
+pub mod synthetic;
 
 aoc_lib! { year = 2021 }
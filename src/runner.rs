@@ -0,0 +1,99 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use aoc_runner::{ArcStr, Runner};
+
+/// Builds the aoc-runner wrapper for a solution, running its generator in the process.
+type MakeRunner = fn(ArcStr) -> Result<Box<dyn Runner>, Box<dyn Error>>;
+
+/// A single `#[aoc(dayN, partM)]` solution, as registered with aoc-runner.
+pub struct Solution {
+    pub day: u32,
+    pub part: u32,
+    pub name: Option<&'static str>,
+    factory: MakeRunner,
+}
+
+/// Every registered solution, sorted by day, part and name.  Generated by `build.rs` from the
+/// `#[aoc]` attributes in this crate.
+pub static SOLUTIONS: &[Solution] = include!(concat!(env!("OUT_DIR"), "/solutions.rs"));
+
+/// The answer produced by a solution, along with how long each stage took.
+pub struct Outcome {
+    pub answer: Box<dyn Display>,
+    pub generator: Duration,
+    pub runner: Duration,
+}
+
+impl Solution {
+    /// Runs the generator (if any) over `input`, returning a runner that can be used to solve the
+    /// part repeatedly.  Trailing newlines are stripped first, as in `run`.
+    pub fn generate(&self, input: &str) -> Result<Box<dyn Runner>, Box<dyn Error>> {
+        (self.factory)(ArcStr::from(input))
+    }
+
+    /// Runs the generator (if any) and then the solver over `input`.  Trailing newlines are
+    /// stripped first, matching what `cargo aoc` passes to the solutions.
+    pub fn run(&self, input: &str) -> Result<Outcome, Box<dyn Error>> {
+        let start = Instant::now();
+        let runner = self.generate(input)?;
+        let generated = Instant::now();
+        let answer = runner.try_run()?;
+        let finished = Instant::now();
+
+        Ok(Outcome {
+            answer,
+            generator: generated - start,
+            runner: finished - generated,
+        })
+    }
+}
+
+impl Display for Solution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Day {} - Part {}", self.day, self.part)?;
+        if let Some(name) = self.name {
+            write!(f, " - {name}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the solutions registered for `day`, optionally restricted to a single `part`.
+pub fn find(day: u32, part: Option<u32>) -> impl Iterator<Item = &'static Solution> {
+    SOLUTIONS
+        .iter()
+        .filter(move |s| s.day == day && part.is_none_or(|p| s.part == p))
+}
+
+/// Returns every day with at least one registered solution, in ascending order.
+pub fn days() -> Vec<u32> {
+    let mut days = SOLUTIONS.iter().map(|s| s.day).collect::<Vec<_>>();
+    days.dedup();
+    days
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn discovers_registered_solutions() {
+        assert_eq!(days(), &[1, 2, 3, 4, 5, 9]);
+        assert_eq!(find(5, None).count(), 2);
+        assert_eq!(find(5, Some(2)).map(|s| s.part).collect::<Vec<_>>(), &[2]);
+        assert_eq!(find(6, None).count(), 0);
+    }
+
+    #[test]
+    fn run_sample_input() {
+        let solution = find(5, Some(2)).next().unwrap();
+        let outcome = solution
+            .run(include_str!("../input/2021/day5_test.txt"))
+            .unwrap();
+        assert_eq!(outcome.answer.to_string(), "12");
+    }
+}
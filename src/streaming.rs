@@ -0,0 +1,178 @@
+//! Solvers that read their input a line at a time from any [`BufRead`], for inputs too large to
+//! load into memory.  Only parts that can be solved in a single pass with bounded state are
+//! available here.
+
+use std::{fmt::Display, io::BufRead};
+
+use crate::{day1, day2, day3, day5, error};
+
+type Solve = fn(&mut dyn BufRead) -> error::Result<Box<dyn Display>>;
+
+/// A part that can be solved from a stream of lines.
+pub struct StreamingSolution {
+    pub day: u32,
+    pub part: u32,
+    solve: Solve,
+}
+
+/// Every streaming solution, sorted by day and part.
+pub static SOLUTIONS: &[StreamingSolution] = &[
+    StreamingSolution {
+        day: 1,
+        part: 1,
+        solve: |r| Ok(Box::new(day1::count_window_increases_streaming(r, 1)?)),
+    },
+    StreamingSolution {
+        day: 1,
+        part: 2,
+        solve: |r| Ok(Box::new(day1::count_window_increases_streaming(r, 3)?)),
+    },
+    StreamingSolution {
+        day: 2,
+        part: 1,
+        solve: |r| Ok(Box::new(day2::part1_streaming(r)?)),
+    },
+    StreamingSolution {
+        day: 2,
+        part: 2,
+        solve: |r| Ok(Box::new(day2::part2_streaming(r)?)),
+    },
+    StreamingSolution {
+        day: 3,
+        part: 1,
+        solve: |r| Ok(Box::new(day3::part1_streaming(r)?)),
+    },
+    StreamingSolution {
+        day: 5,
+        part: 1,
+        solve: |r| Ok(Box::new(day5::overlaps_streaming(r, false)?)),
+    },
+    StreamingSolution {
+        day: 5,
+        part: 2,
+        solve: |r| Ok(Box::new(day5::overlaps_streaming(r, true)?)),
+    },
+];
+
+impl StreamingSolution {
+    /// Solves the part, reading the input from `input`.
+    pub fn solve(&self, input: &mut dyn BufRead) -> error::Result<Box<dyn Display>> {
+        (self.solve)(input)
+    }
+}
+
+impl std::fmt::Display for StreamingSolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Day {} - Part {} - streaming", self.day, self.part)
+    }
+}
+
+/// Returns the streaming solutions for `day`, optionally restricted to a single `part`.
+pub fn find(day: u32, part: Option<u32>) -> impl Iterator<Item = &'static StreamingSolution> {
+    SOLUTIONS
+        .iter()
+        .filter(move |s| s.day == day && part.is_none_or(|p| s.part == p))
+}
+
+/// Calls `f` with each line of `input`, without its line ending, along with its 1-based line
+/// number.  Like the in-memory solutions, which get their input with trailing newlines stripped,
+/// blank lines at the end are dropped, but blank lines before any other line are passed on.
+/// Reuses a single buffer, so memory use doesn't depend on the size of the input.  Returns the
+/// number of lines passed to `f`.
+pub(crate) fn for_each_line(
+    mut input: impl BufRead,
+    mut f: impl FnMut(usize, &str) -> error::Result<()>,
+) -> error::Result<usize> {
+    let mut buf = String::new();
+    let mut line_no = 0;
+    // blank lines read since the last other line, not passed on until we know they aren't trailing
+    let mut blank = 0;
+    loop {
+        buf.clear();
+        if input.read_line(&mut buf)? == 0 {
+            return Ok(line_no - blank);
+        }
+        line_no += 1;
+        let line = buf.strip_suffix('\n').unwrap_or(&buf);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.is_empty() {
+            blank += 1;
+            continue;
+        }
+        for blank_no in line_no - blank..line_no {
+            f(blank_no, "")?;
+        }
+        blank = 0;
+        f(line_no, line)?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::Error, runner};
+
+    #[test]
+    fn matches_in_memory_solutions() {
+        for streaming in SOLUTIONS {
+            let path = format!("input/2021/day{}.txt", streaming.day);
+            let input = std::fs::read_to_string(&path).unwrap();
+            let expected = runner::find(streaming.day, Some(streaming.part))
+                .next()
+                .unwrap()
+                .run(&input)
+                .unwrap()
+                .answer
+                .to_string();
+
+            let answer = streaming.solve(&mut input.as_bytes()).unwrap();
+            assert_eq!(answer.to_string(), expected, "{streaming}");
+        }
+    }
+
+    #[test]
+    fn blank_lines_match_in_memory_solutions() {
+        let lines = |day| match day {
+            1 => ["199", "200", "208"],
+            2 => ["forward 5", "down 5", "up 3"],
+            3 => ["101", "011", "110"],
+            5 => ["0,9 -> 5,9", "8,0 -> 0,8", "0,9 -> 2,9"],
+            _ => unreachable!("no streaming solution for day {day}"),
+        };
+        for streaming in SOLUTIONS {
+            let [first, second, third] = lines(streaming.day);
+            for input in [
+                format!("{first}\n\n{second}\n{third}"),
+                format!("\n{first}\n{second}\n{third}"),
+                format!("{first}\r\n\r\n{second}\r\n{third}\r\n"),
+                format!("{first}\n{second}\n{third}\n\n\n"),
+            ] {
+                let expected = runner::find(streaming.day, Some(streaming.part))
+                    .next()
+                    .unwrap()
+                    .run(&input)
+                    .map(|outcome| outcome.answer.to_string())
+                    .map_err(|e| e.to_string());
+                let answer = streaming
+                    .solve(&mut input.as_bytes())
+                    .map(|answer| answer.to_string())
+                    .map_err(|e| e.to_string());
+                assert_eq!(answer, expected, "{streaming} on {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        let mut input = "199\n200\n201\n2x8\n".as_bytes();
+        let solution = find(1, Some(1)).next().unwrap();
+        assert!(matches!(
+            solution.solve(&mut input),
+            Err(Error::Parse { line: 4, .. })
+        ));
+        assert_eq!(
+            solution.solve(&mut "\n\n".as_bytes()).err(),
+            Some(Error::EmptyInput)
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Random, but valid, puzzle inputs of arbitrary size for stress testing the solutions.
+//!
+//! Every generator is driven by a caller supplied RNG; [`generate`] seeds a [`ChaCha8Rng`] so the
+//! same seed always produces the same input.
+
+use std::{collections::VecDeque, fmt::Write};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::grid::Grid;
+
+/// Highest number that can be drawn in a day 4 game.
+const BINGO_MAX: u8 = 99;
+
+/// Side length of a day 4 board.
+const BOARD_SIZE: usize = 5;
+
+/// Average number of cells per basin in a day 9 heightmap.
+const BASIN_AREA: usize = 40;
+
+/// Fewest basins a day 9 heightmap needs for part 2 to have an answer.
+const MIN_BASINS: usize = 3;
+
+/// Shortest side of a day 9 heightmap.  Smaller maps are mostly walls, and rarely have room for
+/// three basins.
+const MIN_SIDE: usize = 4;
+
+/// Generates an input for `day` from `seed`.  `size` is the number of depths, commands, readings,
+/// boards or line segments for days 1 to 5, and the side length of the heightmap for day 9.
+/// `bits` is the width of the day 3 readings, and is ignored for other days.  Returns `None` for
+/// days without a generator, and for heightmaps too small to hold three basins.
+pub fn generate(day: u32, size: usize, bits: usize, seed: u64) -> Option<String> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let rng = &mut rng;
+    Some(match day {
+        1 => day1(rng, size),
+        2 => day2(rng, size),
+        3 => day3(rng, size, bits),
+        4 => day4(rng, size),
+        5 => day5(rng, size, 1000),
+        9 => day9(rng, size, size)?,
+        _ => return None,
+    })
+}
+
+/// A sonar sweep of `count` depths, wandering mostly downwards.
+pub fn day1(rng: &mut impl Rng, count: usize) -> String {
+    let mut depth: i32 = rng.random_range(100..200);
+    let mut out = String::new();
+    for i in 0..count {
+        if i > 0 {
+            out.push('\n');
+        }
+        write!(out, "{depth}").unwrap();
+        depth = (depth + rng.random_range(-10..=15)).max(0);
+    }
+    out
+}
+
+/// A course of `count` submarine commands.
+pub fn day2(rng: &mut impl Rng, count: usize) -> String {
+    let mut out = String::new();
+    for i in 0..count {
+        if i > 0 {
+            out.push('\n');
+        }
+        let command = ["forward", "down", "up"][rng.random_range(0..3)];
+        write!(out, "{command} {}", rng.random_range(1..=9)).unwrap();
+    }
+    out
+}
+
+/// A diagnostic report of `count` readings, each `bits` wide, that has both ratings.  Answers for
+/// readings wider than 64 bits may still be too large to return.
+pub fn day3(rng: &mut impl Rng, count: usize, bits: usize) -> String {
+    loop {
+        let mut out = String::with_capacity(count * (bits + 1));
+        for i in 0..count {
+            if i > 0 {
+                out.push('\n');
+            }
+            for _ in 0..bits {
+                out.push(if rng.random() { '1' } else { '0' });
+            }
+        }
+        if has_co2_rating(out.as_bytes(), count, bits) {
+            return out;
+        }
+    }
+}
+
+/// Whether filtering `count` readings `bits` wide, laid out as by `day3`, for the CO2 scrubber
+/// rating leaves any.  The oxygen generator rating always exists, since the most common bit is
+/// never missing from the readings left, but the least common bit can be.
+fn has_co2_rating(report: &[u8], count: usize, bits: usize) -> bool {
+    let bit = |reading: usize, column: usize| report[reading * (bits + 1) + column] == b'1';
+    let mut remaining = (0..count).collect::<Vec<_>>();
+    for column in 0..bits {
+        let ones = remaining.iter().filter(|&&r| bit(r, column)).count();
+        let zeros = remaining.len() - ones;
+        if ones == 0 || zeros == 0 {
+            // no reading has the least common bit, which is only fine if filtering has already
+            // stopped because the readings left are copies of one
+            return remaining
+                .windows(2)
+                .all(|pair| (column..bits).all(|c| bit(pair[0], c) == bit(pair[1], c)));
+        }
+        // least common bit, keeping zeros on a tie
+        let kept = ones < zeros;
+        remaining.retain(|&r| bit(r, column) == kept);
+    }
+    true
+}
+
+/// A bingo game with `boards` boards.  Every number is eventually drawn, so every board wins.
+pub fn day4(rng: &mut impl Rng, boards: usize) -> String {
+    let mut numbers = (0..=BINGO_MAX).collect::<Vec<_>>();
+    numbers.shuffle(rng);
+    let mut out = numbers
+        .iter()
+        .map(u8::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    for _ in 0..boards {
+        out.push('\n');
+        numbers.shuffle(rng);
+        for row in numbers[..BOARD_SIZE * BOARD_SIZE].chunks(BOARD_SIZE) {
+            out.push('\n');
+            let row = row.iter().map(|n| format!("{n:>2}")).collect::<Vec<_>>();
+            out.push_str(&row.join(" "));
+        }
+    }
+    out
+}
+
+/// `count` horizontal, vertical and diagonal vent lines, lying in a `dim` by `dim` area.
+pub fn day5(rng: &mut impl Rng, count: usize, dim: u32) -> String {
+    let mut out = String::new();
+    for i in 0..count {
+        if i > 0 {
+            out.push('\n');
+        }
+        let x1 = rng.random_range(0..dim);
+        let y1 = rng.random_range(0..dim);
+        let (x2, y2) = match rng.random_range(0..3) {
+            0 => (rng.random_range(0..dim), y1),
+            1 => (x1, rng.random_range(0..dim)),
+            _ => {
+                // walk diagonally, stopping at whichever edge comes first
+                let right = rng.random::<bool>();
+                let down = rng.random::<bool>();
+                let room_x = if right { dim - 1 - x1 } else { x1 };
+                let room_y = if down { dim - 1 - y1 } else { y1 };
+                let len = rng.random_range(0..=room_x.min(room_y));
+                (
+                    if right { x1 + len } else { x1 - len },
+                    if down { y1 + len } else { y1 - len },
+                )
+            }
+        };
+        write!(out, "{x1},{y1} -> {x2},{y2}").unwrap();
+    }
+    out
+}
+
+/// A `width` by `height` heightmap with at least three basins.  Like the real inputs, basins are
+/// walled off by 9s and each one has a single low point.  Returns `None` if either side is shorter
+/// than `MIN_SIDE`.
+pub fn day9(rng: &mut impl Rng, width: usize, height: usize) -> Option<String> {
+    if width < MIN_SIDE || height < MIN_SIDE {
+        return None;
+    }
+    // small maps often have fewer basins than centres, since walls can take up whole regions
+    loop {
+        let (heights, basins) = heightmap(rng, width, height);
+        if basins >= MIN_BASINS {
+            return Some(heights.to_string());
+        }
+    }
+}
+
+/// A `width` by `height` heightmap, and how many basins it has.
+fn heightmap(rng: &mut impl Rng, width: usize, height: usize) -> (Grid<u8>, usize) {
+    // carve the map into regions around random centres, and wall off the borders between them
+    const UNOWNED: usize = usize::MAX;
+    let mut owner = Grid::new(width, height, UNOWNED);
+    let mut queue = VecDeque::new();
+    for centre in 0..(width * height / BASIN_AREA).max(MIN_BASINS) {
+        let (x, y) = (rng.random_range(0..width), rng.random_range(0..height));
+        if owner[(x, y)] == UNOWNED {
+            owner[(x, y)] = centre;
+            queue.push_back((x, y));
+        }
+    }
+    while let Some((x, y)) = queue.pop_front() {
+        for n in owner.neighbours4(x, y).collect::<Vec<_>>() {
+            if owner[n] == UNOWNED {
+                owner[n] = owner[(x, y)];
+                queue.push_back(n);
+            }
+        }
+    }
+
+    const UNSET: u8 = u8::MAX;
+    let mut heights = Grid::new(width, height, UNSET);
+    for (x, y) in owner.positions() {
+        if owner.neighbours4(x, y).any(|n| owner[n] != owner[(x, y)]) {
+            heights[(x, y)] = 9;
+        }
+    }
+
+    // heights rise with distance from the first point found in each basin, which makes that point
+    // the basin's only low point
+    let mut basins = 0;
+    for start in owner.positions() {
+        if heights[start] != UNSET {
+            continue;
+        }
+        basins += 1;
+        heights[start] = 0;
+        queue.push_back(start);
+        while let Some((x, y)) = queue.pop_front() {
+            let next = (heights[(x, y)] + 1).min(8);
+            for n in heights.neighbours4(x, y).collect::<Vec<_>>() {
+                if heights[n] == UNSET {
+                    heights[n] = next;
+                    queue.push_back(n);
+                }
+            }
+        }
+    }
+
+    (heights, basins)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runner;
+
+    #[test]
+    fn same_seed_same_input() {
+        for day in runner::days() {
+            assert_eq!(generate(day, 20, 12, 7), generate(day, 20, 12, 7));
+        }
+        assert_ne!(generate(1, 20, 12, 7), generate(1, 20, 12, 8));
+        assert_eq!(generate(6, 20, 12, 7), None);
+    }
+
+    /// Runs every solution for `day` on an input generated with the given parameters.
+    fn assert_solvable(day: u32, size: usize, bits: usize, seed: u64) {
+        let Some(input) = generate(day, size, bits, seed) else {
+            assert!(
+                day == 9 && size < MIN_SIDE,
+                "no day {day} input of size {size}"
+            );
+            return;
+        };
+        for solution in runner::find(day, None) {
+            if let Err(e) = solution.run(&input) {
+                panic!("{solution} failed on size {size}, {bits} bits, seed {seed}: {e}\n{input}");
+            }
+        }
+    }
+
+    #[test]
+    fn generated_inputs_are_solvable() {
+        for day in runner::days() {
+            let widths: &[usize] = if day == 3 { &[1, 2, 12, 64] } else { &[12] };
+            for size in [1, 2, 3, 4, 10, 100] {
+                for &bits in widths {
+                    for seed in 0..40 {
+                        assert_solvable(day, size, bits, seed);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn co2_rating() {
+        // the least common first bit is a one, and the one reading with it is the rating
+        assert!(has_co2_rating(b"10\n00\n01", 3, 2));
+        // neither reading left after the first bit has a one in the second bit
+        assert!(!has_co2_rating(b"000\n001\n100\n110\n111", 5, 3));
+        // ... which is fine if they are copies of one reading
+        assert!(has_co2_rating(b"000\n000\n100\n110\n111", 5, 3));
+    }
+}
@@ -0,0 +1,90 @@
+//! Runs every registered solution against the real inputs in `input/2021/` and checks the
+//! answers against `tests/answers.txt`.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use advent_of_code_2021::runner;
+
+const INPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/input/2021");
+
+struct Answer {
+    file: String,
+    day: u32,
+    part: u32,
+    expected: String,
+}
+
+fn manifest() -> Vec<Answer> {
+    include_str!("answers.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+            let [file, part, expected] = fields[..] else {
+                panic!("malformed manifest line {line:?}");
+            };
+            let day = file
+                .strip_prefix("day")
+                .and_then(|s| s.split(|c: char| !c.is_ascii_digit()).next())
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_else(|| panic!("can't determine day from {file:?}"));
+            Answer {
+                file: file.to_string(),
+                day,
+                part: part.parse().unwrap(),
+                expected: expected.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn manifest_covers_every_input() {
+    let manifest = manifest();
+    let listed = manifest
+        .iter()
+        .map(|a| a.file.as_str())
+        .collect::<BTreeSet<_>>();
+    for entry in fs::read_dir(INPUT_DIR).unwrap() {
+        let name = entry.unwrap().file_name().into_string().unwrap();
+        assert!(
+            listed.contains(name.as_str()),
+            "no answers listed for {name}"
+        );
+    }
+
+    // every registered part must have an answer for its day's real input
+    for solution in runner::SOLUTIONS {
+        let file = format!("day{}.txt", solution.day);
+        assert!(
+            manifest
+                .iter()
+                .any(|a| a.file == file && a.part == solution.part),
+            "no answer listed for {file} part {}",
+            solution.part
+        );
+    }
+}
+
+#[test]
+fn known_answers() {
+    let mut failures = vec![];
+    for answer in manifest() {
+        let input = fs::read_to_string(Path::new(INPUT_DIR).join(&answer.file)).unwrap();
+        let solutions = runner::find(answer.day, Some(answer.part)).collect::<Vec<_>>();
+        assert!(!solutions.is_empty(), "no solution for {}", answer.file);
+
+        for solution in solutions {
+            match solution.run(&input) {
+                Ok(outcome) if outcome.answer.to_string() == answer.expected => {}
+                Ok(outcome) => failures.push(format!(
+                    "{solution} on {}: expected {}, got {}",
+                    answer.file, answer.expected, outcome.answer
+                )),
+                Err(e) => failures.push(format!("{solution} on {}: {e}", answer.file)),
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}